@@ -1,160 +1,999 @@
 use std::{
+    collections::HashSet,
     fs,
-    io::{BufRead, BufReader, Write},
-    process::{Command, Stdio},
+    io::Write,
+    path::Path,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
 };
 
 use anyhow::Ok;
+use chrono::TimeZone;
 use git2::{Oid, Repository};
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Regex;
 
 use crate::{
-    get_next_version,
-    git::CommitWrapper,
-    iterate_to_last_version,
+    dependencies::{self, DEPENDENCIES_HEADING, LOCKFILES, LOCKFILE_HEADING},
+    fragment::{self, Fragment},
+    get_next_version, get_release_version,
+    git::{CommitWrapper, TrackedState},
+    iterate_to_last_version, rpm,
     semver::{SemVer, SemVerBump},
-    Config, HistoryItem, Project,
+    summarizer::{
+        self, Backend, CommitInput, Fallback, Prompt, PromptContext, Protocol, SummaryCache,
+    },
+    ChangelogFormat, Config, HistoryItem, HistoryItemKind, Person, Project,
 };
 
-#[derive(Debug, PartialEq, Eq)]
+mod merge;
+mod parse;
+
+const BREAKING_CHANGES_HEADING: &str = "Breaking changes";
+const CHANGES_HEADING: &str = "Changes";
+const CONTRIBUTORS_HEADING: &str = "Contributors";
+/// How many of the newest releases the summarizer sees as style samples
+const SUMMARIZER_SAMPLES: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum ChangeLogEntry {
     BumpVersion(SemVer),
     InitialVersion(SemVer),
+    /// The date of the preceding version, where it is known
+    ReleaseDate(String),
+    /// A heading grouping the entries which follow it
+    Category(String),
     Entry(String),
 }
 
+/// A version and the changes that went into it
+#[derive(Debug, Clone)]
+struct Release {
+    version: SemVer,
+    previous_version: SemVer,
+    /// Unix time of the release commit, `None` for the release being made
+    date: Option<i64>,
+    commits: Vec<Oid>,
+    entries: Vec<ReleaseEntry>,
+    /// What users need to know about the breaking changes in the release
+    breaking_changes: Vec<ReleaseEntry>,
+    /// Everyone credited in the release, oldest commit first
+    contributors: Vec<Contributor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Contributor {
+    person: Person,
+    /// This release has their first commit in the repo
+    first_time: bool,
+}
+
+impl Contributor {
+    fn render(&self) -> String {
+        if self.first_time {
+            format!("{} (first contribution)", self.person.name)
+        } else {
+            self.person.name.clone()
+        }
+    }
+}
+
+/// An entry in a release and the commit it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReleaseEntry {
+    text: String,
+    commit: Option<Oid>,
+    /// Marked as breaking by something other than the text, like a trailer
+    breaking: bool,
+    /// The heading the entry is listed under, if it is not a main entry
+    section: Option<String>,
+}
+
+impl ReleaseEntry {
+    fn new(text: String) -> Self {
+        Self {
+            text,
+            commit: None,
+            breaking: false,
+            section: None,
+        }
+    }
+
+    /// The entry text as it should read in the changelog, without links
+    fn formatted(&self, config: &Config) -> String {
+        // package names keep their case
+        let is_dependency = matches!(
+            self.section.as_deref(),
+            Some(DEPENDENCIES_HEADING | LOCKFILE_HEADING)
+        );
+        if config.clean_entries && !is_dependency {
+            clean_entry_text(&self.text, self.breaking)
+        } else {
+            self.text.clone()
+        }
+    }
+
+    fn render(&self, config: &Config) -> String {
+        let text = self.formatted(config);
+        match &config.links {
+            Some(links) => links.linkify(&text, self.commit),
+            None => text,
+        }
+    }
+}
+
+impl Release {
+    fn changelog_entries<'a>(
+        &'a self,
+        config: &'a Config,
+    ) -> impl Iterator<Item = ChangeLogEntry> + 'a {
+        let date = self.date.map(|t| {
+            chrono::Local
+                .timestamp_opt(t, 0)
+                .unwrap()
+                .format("%Y-%m-%d")
+                .to_string()
+        });
+        std::iter::once(ChangeLogEntry::BumpVersion(self.version.clone()))
+            .chain(date.map(ChangeLogEntry::ReleaseDate))
+            .chain(self.breaking_change_entries(config))
+            .chain(
+                self.entries
+                    .iter()
+                    .filter(|e| e.section.is_none())
+                    .map(|e| ChangeLogEntry::Entry(e.render(config))),
+            )
+            .chain(self.section_entries(config))
+            .chain(self.contributor_entries())
+    }
+
+    /// Entries from the include rules, grouped under their sections in the
+    /// order the rules are configured
+    fn section_entries(&self, config: &Config) -> Vec<ChangeLogEntry> {
+        let sections = config
+            .include
+            .iter()
+            .filter_map(|rule| rule.section.as_deref())
+            .chain(self.entries.iter().filter_map(|e| e.section.as_deref()))
+            .unique();
+        let mut changelog_entries = vec![];
+        for section in sections {
+            let mut entries = self
+                .entries
+                .iter()
+                .filter(|e| e.section.as_deref() == Some(section))
+                .peekable();
+            if entries.peek().is_none() {
+                continue;
+            }
+            changelog_entries.push(ChangeLogEntry::Category(section.to_string()));
+            changelog_entries.extend(entries.map(|e| ChangeLogEntry::Entry(e.render(config))));
+        }
+        changelog_entries
+    }
+
+    /// The breaking changes block, and a heading to set the other entries
+    /// apart from it
+    fn breaking_change_entries(&self, config: &Config) -> Vec<ChangeLogEntry> {
+        if self.breaking_changes.is_empty() {
+            return vec![];
+        }
+        let mut entries = vec![ChangeLogEntry::Category(
+            BREAKING_CHANGES_HEADING.to_string(),
+        )];
+        entries.extend(
+            self.breaking_changes
+                .iter()
+                .map(|e| ChangeLogEntry::Entry(e.render(config))),
+        );
+        if self.entries.iter().any(|e| e.section.is_none()) {
+            entries.push(ChangeLogEntry::Category(CHANGES_HEADING.to_string()));
+        }
+        entries
+    }
+
+    fn contributor_entries(&self) -> Vec<ChangeLogEntry> {
+        if self.contributors.is_empty() {
+            return vec![];
+        }
+        std::iter::once(ChangeLogEntry::Category(CONTRIBUTORS_HEADING.to_string()))
+            .chain(
+                self.contributors
+                    .iter()
+                    .map(|c| ChangeLogEntry::Entry(c.render())),
+            )
+            .collect()
+    }
+}
+
+/// Write the changelog for the release being made, with `fragments` as its
+/// first entries, and use the fragments up
 pub fn prepare_changelog<T: Iterator<Item = CommitWrapper> + Clone>(
     history: T,
     repo: Option<&Repository>,
     project: &dyn Project,
+    fragments: &[Fragment],
     config: &Config,
 ) -> anyhow::Result<()> {
     let path = project.get_dir().join(project.get_changelog());
-    if !path.exists() {
-        generate_entire_changelog(history, repo, project, config)
+    let releases = if !path.exists() {
+        generate_entire_changelog(history, repo, project, fragments, config)?
     } else {
-        append_changelog(history, repo, project, config)
+        let samples = changelog_samples(project);
+        append_changelog(history, repo, project, fragments, &samples, config)?
+    };
+    update_spec_files(&releases, repo, project, config)?;
+    update_json_release_notes(&releases, project, config)?;
+    if !releases.is_empty() {
+        fragment::remove(fragments)?;
     }
+    Ok(())
+}
+
+/// Mirror the newest release in the `%changelog` of any RPM spec files
+fn update_spec_files(
+    releases: &[Release],
+    repo: Option<&Repository>,
+    project: &dyn Project,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let specs: Vec<_> = project
+        .get_spec_file()
+        .into_iter()
+        .chain(config.rpm_spec.as_deref())
+        .map(|p| project.get_dir().join(p))
+        // `rpm_spec` may name the spec file the project already is
+        .unique_by(|p| fs::canonicalize(p).unwrap_or_else(|_| p.clone()))
+        .collect();
+    let release = match releases.first() {
+        Some(r) if !specs.is_empty() => r,
+        _ => return Ok(()),
+    };
+
+    let packager = match repo {
+        Some(r) => {
+            let sig = crate::git::release_signature(r, config)?;
+            format!(
+                "{} <{}>",
+                sig.name().unwrap_or(""),
+                sig.email().unwrap_or("")
+            )
+        }
+        None => format!("{} <{}>", config.name, config.email),
+    };
+    let date = chrono::Local::now().format("%a %b %d %Y").to_string();
+
+    let mut entries: Vec<String> = release
+        .breaking_changes
+        .iter()
+        .map(|e| format!("Breaking change: {}", e.formatted(config)))
+        .chain(release.entries.iter().map(|e| e.formatted(config)))
+        .collect();
+    if !release.contributors.is_empty() {
+        let names = release.contributors.iter().map(|c| c.render()).join(", ");
+        entries.push(format!("{}: {}", CONTRIBUTORS_HEADING, names));
+    }
+    for spec in specs {
+        rpm::write_spec_release(&spec, &release.version, &date, &packager, &entries)?;
+    }
+    Ok(())
+}
+
+/// Add the new releases to the JSON release notes, replacing any records
+/// for the same versions
+fn update_json_release_notes(
+    releases: &[Release],
+    project: &dyn Project,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let path = match &config.release_notes_json {
+        Some(p) if !releases.is_empty() => project.get_dir().join(p),
+        _ => return Ok(()),
+    };
+
+    let mut records: Vec<json::ReleaseRecord> = releases.iter().map(json::record).collect();
+    if path.exists() {
+        let existing: Vec<json::ReleaseRecord> = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        records.extend(existing.into_iter().filter(|old| {
+            releases
+                .iter()
+                .all(|r| r.version.to_string() != old.version)
+        }));
+    }
+    fs::write(&path, json::render(&records)?)?;
+    Ok(())
 }
 
 fn append_changelog<T: Iterator<Item = CommitWrapper> + Clone>(
     history: T,
     repo: Option<&Repository>,
     project: &dyn Project,
+    fragments: &[Fragment],
+    samples: &str,
     config: &Config,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Vec<Release>> {
     let path = project.get_dir().join(project.get_changelog());
 
-    let changelog_entries = get_newest_changelog_items(history, repo, config)?;
-    if changelog_entries.is_empty() {
-        return Ok(());
+    let release = match get_newest_release(history, repo, fragments, samples, config)? {
+        Some(r) => r,
+        None => return Ok(vec![]),
+    };
+    let mut releases = vec![release];
+    let changelog_entries: Vec<_> = releases[0].changelog_entries(config).collect();
+    let mut new_changelog = render::render_releases(&changelog_entries, config);
+    if config.edit_changelog {
+        new_changelog = edit_releases(&new_changelog, &mut releases, repo, config)?;
     }
-    let original = fs::read_to_string(&path)?;
-    let changelog = render::prepend_render_changelog(&changelog_entries, &original, config);
+
+    let release = &releases[0];
+    let mut original = fs::read_to_string(&path)?;
+    if parse::contains_version(&original, &release.version) {
+        eprintln!(
+            "Replacing the existing section for {} in {}",
+            release.version,
+            path.display()
+        );
+        original = parse::remove_release(&original, &release.version);
+    }
+    let changelog = render::insert_releases(&new_changelog, &original, config);
 
     fs::write(&path, changelog)?;
-    Ok(())
+    Ok(releases)
 }
 
-fn get_newest_changelog_items<T: Iterator<Item = impl HistoryItem> + Clone>(
+/// Let the user change the rendered releases in their editor, and carry the
+/// edited entries back into the releases so other outputs match.
+fn edit_releases(
+    rendered: &str,
+    releases: &mut [Release],
+    repo: Option<&Repository>,
+    config: &Config,
+) -> anyhow::Result<String> {
+    let edited = crate::git::edit_text(repo, rendered)?;
+    if edited.trim().is_empty() {
+        anyhow::bail!("Aborting release due to empty changelog");
+    }
+
+    let parsed = parse::parse_changelog(&edited);
+    for release in releases.iter_mut() {
+        let is_heading = |e: &ChangeLogEntry| {
+            matches!(
+                e,
+                ChangeLogEntry::BumpVersion(v) | ChangeLogEntry::InitialVersion(v)
+                    if *v == release.version
+            )
+        };
+        let Some(start) = parsed.iter().position(is_heading) else {
+            anyhow::bail!(
+                "The heading for version {} is missing from the edited changelog, \
+                 so its entries cannot be read back. Keep the version headings as they are.",
+                release.version
+            );
+        };
+        let section = parsed[start + 1..].iter().take_while(|e| {
+            !matches!(
+                e,
+                ChangeLogEntry::BumpVersion(_) | ChangeLogEntry::InitialVersion(_)
+            )
+        });
+
+        // keep the commit of entries which were left alone
+        let find = |originals: &[ReleaseEntry], text: &String, section: Option<&str>| {
+            let mut entry = originals
+                .iter()
+                .find(|original| original.render(config) == *text)
+                .cloned()
+                .unwrap_or_else(|| ReleaseEntry::new(text.clone()));
+            entry.section = section.map(String::from);
+            entry
+        };
+        let mut entries = vec![];
+        let mut breaking_changes = vec![];
+        let mut category = None;
+        for entry in section {
+            match entry {
+                ChangeLogEntry::Category(name) => category = Some(name.as_str()),
+                ChangeLogEntry::Entry(text) => match category {
+                    Some(BREAKING_CHANGES_HEADING) => {
+                        breaking_changes.push(find(&release.breaking_changes, text, None))
+                    }
+                    Some(CONTRIBUTORS_HEADING) => {}
+                    None | Some(CHANGES_HEADING) => {
+                        entries.push(find(&release.entries, text, None))
+                    }
+                    section => entries.push(find(&release.entries, text, section)),
+                },
+                _ => {}
+            }
+        }
+        release.entries = entries;
+        release.breaking_changes = breaking_changes;
+    }
+    Ok(edited)
+}
+
+fn get_newest_release<T: Iterator<Item = impl HistoryItem> + Clone>(
     history: T,
     repo: Option<&Repository>,
+    fragments: &[Fragment],
+    samples: &str,
     config: &Config,
-) -> anyhow::Result<Vec<ChangeLogEntry>> {
-    let next_version = match get_next_version(history.clone(), config) {
+) -> anyhow::Result<Option<Release>> {
+    let next_version = match get_release_version(history.clone(), fragments, config) {
         Some(v) => v,
-        None => return Ok(vec![]),
+        None => return Ok(None),
     };
-    let window: Vec<_> = iterate_to_last_version(history).collect();
-    let messages: Vec<String> = window.iter().map(|c| c.message()).collect();
-    let newest_oid = window.first().and_then(|c| c.commit_id());
-    let oldest_oid = window.last().and_then(|c| c.commit_id());
-    let diff = compute_diff(repo, newest_oid, oldest_oid)?;
-
-    let entries = get_entries_for_window(&messages, &diff, config)?;
-    let mut changelog_entries = vec![ChangeLogEntry::BumpVersion(next_version)];
-    changelog_entries.extend(entries.into_iter().map(ChangeLogEntry::Entry));
-    Ok(changelog_entries)
+    let commits: Vec<_> = iterate_to_last_version(history.clone()).collect();
+    let previous_version = commits
+        .first()
+        .map(|c| c.version())
+        .expect("a next version implies a non-empty window");
+    let earlier_authors = if config.contributors {
+        author_keys(
+            history
+                .skip(commits.len())
+                .flat_map(|c| credited_authors(&c)),
+        )
+    } else {
+        HashSet::new()
+    };
+    let window = Window {
+        version: next_version,
+        previous_version,
+        date: None,
+        commits,
+        earlier_authors,
+        fragments: fragments.to_vec(),
+    };
+    make_release(&window, repo, samples, config).map(Some)
 }
 
 fn generate_entire_changelog<T: Iterator<Item = CommitWrapper> + Clone>(
     history: T,
     repo: Option<&Repository>,
     project: &dyn Project,
+    fragments: &[Fragment],
     config: &Config,
-) -> anyhow::Result<()> {
-    let changelog_entries = get_all_changelog_entries(history, repo, config)?;
-    let changelog = render::render_changelog(&changelog_entries, config);
+) -> anyhow::Result<Vec<Release>> {
+    // there is no changelog yet for the summarizer to follow
+    let mut releases = get_all_releases(history.clone(), repo, fragments, "", config)?;
+    let changelog_entries = releases_to_changelog_entries(&releases, history, config);
+    let mut changelog = render::render_changelog(&changelog_entries, config);
+    if config.edit_changelog {
+        changelog = edit_releases(&changelog, &mut releases, repo, config)?;
+    }
     let path = project.get_dir().join(project.get_changelog());
     let mut file = fs::File::create(path)?;
     file.write_all(changelog.as_bytes())
         .expect("failed to make changelog");
+    Ok(releases)
+}
+
+/// Render an existing changelog again in the configured format
+pub fn reformat_changelog(project: &dyn Project, config: &Config) -> anyhow::Result<()> {
+    let path = project.get_dir().join(project.get_changelog());
+    let original = fs::read_to_string(&path)?;
+    let changelog_entries = parse::parse_changelog(&original);
+    if changelog_entries.is_empty() {
+        anyhow::bail!("No released versions found in {}", path.display());
+    }
+    let has_dates = changelog_entries
+        .iter()
+        .any(|e| matches!(e, ChangeLogEntry::ReleaseDate(_)));
+    if has_dates && config.changelog_format == ChangelogFormat::Clog {
+        eprintln!("The clog changelog format has no release dates, so they are left out");
+    }
+    fs::write(&path, render::render_changelog(&changelog_entries, config))?;
+    Ok(())
+}
+
+/// Resolve the conflicts in the project's changelog by merging both sides
+/// by version
+pub fn resolve_changelog(project: &dyn Project, config: &Config) -> anyhow::Result<()> {
+    let path = project.get_dir().join(project.get_changelog());
+    let conflicted = fs::read_to_string(&path)?;
+    let (ours, theirs) = merge::split_conflict(&conflicted)
+        .ok_or_else(|| anyhow::anyhow!("No conflict markers found in {}", path.display()))?;
+    // the conflict has no common ancestor to go by
+    write_merged(&path, merge::merge_changelogs(None, &ours, &theirs, config))
+}
+
+/// Merge `theirs` into `ours`, going by their common ancestor `base` if
+/// there is one, as a git merge driver
+pub fn merge_changelog_files(
+    ours: &Path,
+    theirs: &Path,
+    base: Option<&Path>,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let base = base.map(fs::read_to_string).transpose()?;
+    let merged = merge::merge_changelogs(
+        base.as_deref(),
+        &fs::read_to_string(ours)?,
+        &fs::read_to_string(theirs)?,
+        config,
+    );
+    write_merged(ours, merged)
+}
+
+/// Write a merged changelog, failing if parts of it are left in conflict
+fn write_merged(path: &Path, merged: merge::Merged) -> anyhow::Result<()> {
+    fs::write(path, merged.text)?;
+    if merged.conflicts > 0 {
+        anyhow::bail!(
+            "{} part(s) of {} could not be merged and are left with conflict markers",
+            merged.conflicts,
+            path.display()
+        );
+    }
     Ok(())
 }
 
-fn get_all_changelog_entries<T: Iterator<Item = impl HistoryItem> + Clone>(
+/// Notes for one release, rendered like its section of the changelog. With
+/// no version, the unreleased changes, or else the newest release. The
+/// `fragments` go in the unreleased changes.
+pub fn version_notes<T: Iterator<Item = CommitWrapper> + Clone>(
     history: T,
+    version: Option<&SemVer>,
+    fragments: &[Fragment],
     repo: Option<&Repository>,
+    project: &dyn Project,
     config: &Config,
-) -> anyhow::Result<Vec<ChangeLogEntry>> {
-    let mut bump_to = match get_next_version(history.clone(), config) {
-        Some(v) => v,
-        None => return Ok(vec![]),
+) -> anyhow::Result<String> {
+    let windows = get_windows(history, fragments, config);
+    let window = match version {
+        Some(v) => windows
+            .iter()
+            .find(|w| w.version == *v)
+            .ok_or_else(|| anyhow::anyhow!("No release of version {} in the history", v))?,
+        None => windows
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("No releases in the history"))?,
     };
+    let samples = changelog_samples(project);
+    Ok(render_notes(
+        &make_release(window, repo, &samples, config)?,
+        config,
+    ))
+}
 
-    let mut changelog_entries = vec![];
-    let mut window_messages: Vec<String> = vec![];
-    let mut window_newest_oid: Option<Oid> = None;
-    let mut window_oldest_oid: Option<Oid> = None;
-    let mut window_version: Option<SemVer> = None;
-
-    for commit in history.clone() {
-        let cv = commit.version();
-        match &window_version {
-            None => {
-                window_version = Some(cv);
-            }
-            Some(v) if *v != cv => {
-                let diff = compute_diff(repo, window_newest_oid, window_oldest_oid)?;
-                changelog_entries.push(ChangeLogEntry::BumpVersion(bump_to));
-                let entries = get_entries_for_window(&window_messages, &diff, config)?;
-                changelog_entries.extend(entries.into_iter().map(ChangeLogEntry::Entry));
-                bump_to = v.clone();
-                window_messages.clear();
-                window_newest_oid = None;
-                window_version = Some(cv);
-            }
-            Some(_) => {}
-        }
-        if window_newest_oid.is_none() {
-            window_newest_oid = commit.commit_id();
+/// Notes for the commits in a range, newest first. The commits before the
+/// range are only used to spot first-time contributors.
+pub fn range_notes(
+    range: Vec<CommitWrapper>,
+    earlier: impl Iterator<Item = CommitWrapper>,
+    repo: Option<&Repository>,
+    project: &dyn Project,
+    config: &Config,
+) -> anyhow::Result<String> {
+    let (newest, oldest) = match (range.first(), range.last()) {
+        (Some(newest), Some(oldest)) => (newest, oldest),
+        _ => anyhow::bail!("There are no commits in the range"),
+    };
+    // a range ending at a release commit is that release, otherwise it is
+    // the release those changes would make
+    let (version, date) = match newest.kind() {
+        HistoryItemKind::ClogBump => (newest.version(), newest.time()),
+        HistoryItemKind::Normal => (
+            get_next_version(range.iter().cloned(), config).unwrap_or(newest.version()),
+            None,
+        ),
+    };
+    let window = Window {
+        version,
+        previous_version: oldest.version(),
+        date,
+        earlier_authors: if config.contributors {
+            author_keys(earlier.flat_map(|c| credited_authors(&c)))
+        } else {
+            HashSet::new()
+        },
+        commits: range
+            .into_iter()
+            .filter(|c| c.kind() != HistoryItemKind::ClogBump)
+            .collect(),
+        fragments: vec![],
+    };
+    let samples = changelog_samples(project);
+    Ok(render_notes(
+        &make_release(&window, repo, &samples, config)?,
+        config,
+    ))
+}
+
+/// The newest releases in the project's changelog, for the summarizer to
+/// follow the style of
+fn changelog_samples(project: &dyn Project) -> String {
+    let path = project.get_dir().join(project.get_changelog());
+    let changelog = fs::read_to_string(path).unwrap_or_default();
+    parse::newest_releases(&changelog, SUMMARIZER_SAMPLES)
+}
+
+fn render_notes(release: &Release, config: &Config) -> String {
+    let changelog_entries: Vec<_> = release.changelog_entries(config).collect();
+    render::render_releases(&changelog_entries, config)
+}
+
+#[cfg(test)]
+fn get_all_changelog_entries<T: Iterator<Item = impl HistoryItem + Sync> + Clone>(
+    history: T,
+    repo: Option<&Repository>,
+    config: &Config,
+) -> anyhow::Result<Vec<ChangeLogEntry>> {
+    let releases = get_all_releases(history.clone(), repo, &[], "", config)?;
+    Ok(releases_to_changelog_entries(&releases, history, config))
+}
+
+fn releases_to_changelog_entries<T, H>(
+    releases: &[Release],
+    history: T,
+    config: &Config,
+) -> Vec<ChangeLogEntry>
+where
+    T: Iterator<Item = H>,
+    H: HistoryItem,
+{
+    if releases.is_empty() {
+        return vec![];
+    }
+    let mut changelog_entries: Vec<_> = releases
+        .iter()
+        .flat_map(|r| r.changelog_entries(config))
+        .collect();
+    if let Some(version) = find_first_version_of_project(history) {
+        changelog_entries.push(ChangeLogEntry::InitialVersion(version));
+    }
+    changelog_entries
+}
+
+/// The commits between two version bumps and the version they went into
+struct Window<H> {
+    version: SemVer,
+    previous_version: SemVer,
+    /// Unix time of the release commit, `None` for the release being made
+    date: Option<i64>,
+    /// Newest first
+    commits: Vec<H>,
+    /// Everyone credited in a commit before the window
+    earlier_authors: HashSet<String>,
+    /// Hand-written entries, only for the release being made
+    fragments: Vec<Fragment>,
+}
+
+/// Split the history into the windows between version bumps, newest first.
+/// Unreleased commits which do not need a release are left out, and the
+/// `fragments` go in the unreleased window.
+fn get_windows<T, H>(history: T, fragments: &[Fragment], config: &Config) -> Vec<Window<H>>
+where
+    T: Iterator<Item = H> + Clone,
+    H: HistoryItem,
+{
+    let mut bump_to = get_release_version(history.clone(), fragments, config);
+    let mut fragments = Some(fragments);
+
+    let chunks: Vec<(SemVer, Vec<_>)> = history
+        .chunk_by(|c| c.version())
+        .into_iter()
+        .map(|(version, commits)| (version, commits.collect()))
+        .collect();
+
+    // the authors of all the commits older than each window
+    let mut earlier_authors = vec![HashSet::new(); chunks.len()];
+    if config.contributors {
+        let mut seen = HashSet::new();
+        for (i, (_, commits)) in chunks.iter().enumerate().rev() {
+            earlier_authors[i] = seen.clone();
+            seen.extend(author_keys(commits.iter().flat_map(credited_authors)));
         }
-        window_oldest_oid = commit.commit_id();
-        window_messages.push(commit.message());
     }
 
-    if !window_messages.is_empty() {
-        let diff = compute_diff(repo, window_newest_oid, window_oldest_oid)?;
-        changelog_entries.push(ChangeLogEntry::BumpVersion(bump_to));
-        let entries = get_entries_for_window(&window_messages, &diff, config)?;
-        changelog_entries.extend(entries.into_iter().map(ChangeLogEntry::Entry));
+    let mut windows = vec![];
+    let mut released_at = None;
+    for ((version, commits), earlier_authors) in chunks.into_iter().zip(earlier_authors) {
+        // the oldest commit of a window is the one which bumped the version
+        let bumped_at = commits.last().and_then(|c| c.time());
+        let fragments = fragments.take().unwrap_or_default();
+        if let Some(bump_to) = bump_to {
+            windows.push(Window {
+                version: bump_to,
+                previous_version: version.clone(),
+                date: released_at,
+                commits,
+                earlier_authors,
+                fragments: fragments.to_vec(),
+            });
+        }
+        released_at = bumped_at;
+        bump_to = Some(version);
     }
+    windows
+}
 
-    if let Some(version) = find_first_version_of_project(history) {
-        changelog_entries.push(ChangeLogEntry::InitialVersion(version));
+fn get_all_releases<T: Iterator<Item = impl HistoryItem + Sync> + Clone>(
+    history: T,
+    repo: Option<&Repository>,
+    fragments: &[Fragment],
+    samples: &str,
+    config: &Config,
+) -> anyhow::Result<Vec<Release>> {
+    if get_release_version(history.clone(), fragments, config).is_none() {
+        return Ok(vec![]);
+    }
+    let windows = get_windows(history, fragments, config);
+    // only the summarizer is slow enough to be worth the threads
+    let workers = match config.summarizer_backend() {
+        Some(_) => config.summarizer_concurrency.min(windows.len()),
+        None => 1,
+    };
+    if workers > 1 {
+        return make_releases_in_parallel(&windows, workers, repo, samples, config);
     }
+    windows
+        .iter()
+        .map(|w| make_release(w, repo, samples, config))
+        .collect()
+}
 
-    Ok(changelog_entries)
+/// Make the releases for `windows` on `workers` threads, in the same order
+fn make_releases_in_parallel<H: HistoryItem + Sync>(
+    windows: &[Window<H>],
+    workers: usize,
+    repo: Option<&Repository>,
+    samples: &str,
+    config: &Config,
+) -> anyhow::Result<Vec<Release>> {
+    // a repository cannot be shared between threads, so each opens its own
+    let repo_path = repo.map(|r| r.path().to_path_buf());
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let work = || -> anyhow::Result<Vec<(usize, Release)>> {
+        let repo = repo_path.as_ref().map(Repository::open).transpose()?;
+        let mut releases = vec![];
+        while !failed.load(Ordering::Relaxed) {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            let Some(window) = windows.get(i) else {
+                break;
+            };
+            let release =
+                make_release(window, repo.as_ref(), samples, config).inspect_err(|_| {
+                    failed.store(true, Ordering::Relaxed);
+                })?;
+            releases.push((i, release));
+        }
+        Ok(releases)
+    };
+
+    let mut releases: Vec<(usize, Release)> = thread::scope(|scope| {
+        let handles = (0..workers).map(|_| scope.spawn(work)).collect_vec();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("a summarizer thread panicked"))
+            .flatten_ok()
+            .try_collect()
+    })?;
+    releases.sort_by_key(|(i, _)| *i);
+    Ok(releases.into_iter().map(|(_, release)| release).collect())
 }
 
-fn compute_diff(
+/// The release for a window. `samples` are releases from the changelog for
+/// the summarizer to follow the style of.
+fn make_release<H: HistoryItem>(
+    window: &Window<H>,
     repo: Option<&Repository>,
-    newest: Option<Oid>,
-    oldest: Option<Oid>,
-) -> anyhow::Result<String> {
-    match (repo, newest, oldest) {
-        (Some(r), Some(n), Some(o)) => crate::git::diff_oids(r, n, o),
-        _ => Ok(String::new()),
+    samples: &str,
+    config: &Config,
+) -> anyhow::Result<Release> {
+    let commits = &window.commits;
+    let newest_oid = commits.first().and_then(|c| c.commit_id());
+    let mut entries = get_entries_for_window(window, repo, samples, config)?;
+    if let (true, Some(repo), Some(newest), Some(oldest)) =
+        (config.lockfile_changes, repo, newest_oid, commits.last())
+    {
+        entries.extend(lockfile_entries(repo, newest, oldest)?);
+    }
+    // hand-written notes come before those taken from commits
+    entries.splice(
+        0..0,
+        window.fragments.iter().map(|f| ReleaseEntry {
+            breaking: f.bump == SemVerBump::Major,
+            section: f.category.clone(),
+            ..ReleaseEntry::new(f.text.clone())
+        }),
+    );
+    let breaking_changes = if config.breaking_changes {
+        commits
+            .iter()
+            .filter(|c| c.kind() != HistoryItemKind::ClogBump)
+            .filter_map(|c| {
+                breaking_change_note(&c.message()).map(|text| ReleaseEntry {
+                    text,
+                    commit: c.commit_id(),
+                    breaking: false,
+                    section: None,
+                })
+            })
+            .collect_vec()
+    } else {
+        vec![]
+    };
+    Ok(Release {
+        version: window.version.clone(),
+        previous_version: window.previous_version.clone(),
+        date: window.date,
+        commits: commits.iter().filter_map(|c| c.commit_id()).collect(),
+        entries,
+        breaking_changes,
+        contributors: if config.contributors {
+            get_contributors(commits, &window.earlier_authors)
+        } else {
+            vec![]
+        },
+    })
+}
+
+/// People are told apart by email, as names are often written differently
+fn author_key(person: &Person) -> String {
+    person.email.to_lowercase()
+}
+
+/// The authors of a commit, except for the release commits clog makes
+fn credited_authors(commit: &impl HistoryItem) -> Vec<Person> {
+    match commit.kind() {
+        HistoryItemKind::ClogBump => vec![],
+        HistoryItemKind::Normal => commit.authors(),
+    }
+}
+
+fn author_keys(authors: impl Iterator<Item = Person>) -> HashSet<String> {
+    authors.map(|p| author_key(&p)).collect()
+}
+
+fn get_contributors<H: HistoryItem>(
+    window: &[H],
+    earlier_authors: &HashSet<String>,
+) -> Vec<Contributor> {
+    let mut seen = HashSet::new();
+    window
+        .iter()
+        .rev()
+        .flat_map(credited_authors)
+        .filter(|p| seen.insert(author_key(p)))
+        .map(|person| Contributor {
+            first_time: !earlier_authors.contains(&author_key(&person)),
+            person,
+        })
+        .collect()
+}
+
+/// The package changes in the lockfiles over a window
+fn lockfile_entries<H: HistoryItem>(
+    repo: &Repository,
+    newest: Oid,
+    oldest: &H,
+) -> anyhow::Result<Vec<ReleaseEntry>> {
+    // a release commit only changes the project's own version
+    let base = match (oldest.kind(), oldest.commit_id()) {
+        (HistoryItemKind::ClogBump, id) => id,
+        (HistoryItemKind::Normal, Some(id)) => repo.find_commit(id)?.parent_ids().next(),
+        (HistoryItemKind::Normal, None) => None,
+    };
+    let mut entries = vec![];
+    for lockfile in LOCKFILES {
+        let (before, after) = crate::git::file_before_and_after(repo, newest, base, lockfile)?;
+        let changes = dependencies::lockfile_changes(lockfile, before.as_deref(), after.as_deref());
+        entries.extend(changes.into_iter().map(|text| ReleaseEntry {
+            section: Some(LOCKFILE_HEADING.to_string()),
+            ..ReleaseEntry::new(text)
+        }));
     }
+    Ok(entries)
+}
+
+/// The prompts for a window, from the `summarizer_prompt` template if there
+/// is one. Windows with more diff than the budget allows are split into
+/// runs of commits, newest first, which are summarised one by one.
+fn summarizer_prompts<H: HistoryItem>(
+    window: &Window<H>,
+    repo: Option<&Repository>,
+    samples: &str,
+    config: &Config,
+) -> anyhow::Result<Vec<Prompt>> {
+    let protocol = config.summarizer.protocol;
+    let template = match &config.summarizer_prompt {
+        Some(path) => fs::read_to_string(config.path.join(path)).map_err(|e| {
+            anyhow::anyhow!("Failed to read summarizer_prompt {}: {}", path.display(), e)
+        })?,
+        None if protocol == Protocol::Json => summarizer::DEFAULT_JSON_PROMPT.to_string(),
+        None => summarizer::DEFAULT_PROMPT.to_string(),
+    };
+    let context = PromptContext {
+        version: window.version.to_string(),
+        previous_version: window.previous_version.to_string(),
+        changelog_samples: samples.to_string(),
+        ..Default::default()
+    };
+    let exclude = summarizer::DEFAULT_EXCLUDE
+        .iter()
+        .map(|p| p.to_string())
+        .chain(config.summarizer_exclude.iter().cloned())
+        .collect_vec();
+    let prompt_text = |commits: &[H], context: &PromptContext| -> anyhow::Result<String> {
+        let instructions = summarizer::render_prompt(&template, context);
+        if protocol == Protocol::Lines {
+            return Ok(instructions);
+        }
+        let commits: Vec<CommitInput> = commits
+            .iter()
+            .map(|c| {
+                let files = match (repo, c.commit_id()) {
+                    (Some(repo), Some(oid)) => crate::git::file_diffs(repo, oid, oid, &exclude)?
+                        .into_iter()
+                        .map(|f| f.path)
+                        .collect(),
+                    _ => vec![],
+                };
+                Ok(CommitInput {
+                    hash: c.commit_id().map(|o| o.to_string()).unwrap_or_default(),
+                    author: c
+                        .authors()
+                        .iter()
+                        .map(|p| format!("{} <{}>", p.name, p.email))
+                        .join(", "),
+                    message: c.message(),
+                    files,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(summarizer::json_input(&instructions, context, &commits))
+    };
+
+    let commits = &window.commits;
+    let oids: Vec<Oid> = commits.iter().filter_map(|c| c.commit_id()).collect();
+    let repo = match repo {
+        Some(repo) if !oids.is_empty() && oids.len() == commits.len() => repo,
+        _ => {
+            let context = PromptContext {
+                commits: commits.iter().map(|c| c.message()).collect(),
+                ..context
+            };
+            return Ok(vec![Prompt {
+                text: prompt_text(commits, &context)?,
+                commits: None,
+            }]);
+        }
+    };
+
+    let budget = config.summarizer_diff_budget;
+    let diff_size = |newest, oldest| -> anyhow::Result<usize> {
+        let files = crate::git::file_diffs(repo, newest, oldest, &exclude)?;
+        Ok(files.iter().map(|f| f.patch.len()).sum())
+    };
+    // only weigh the commits one by one when the whole window is too big
+    let sizes: Vec<usize> = if oids.len() > 1 && diff_size(oids[0], oids[oids.len() - 1])? > budget
+    {
+        oids.iter().map(|&o| diff_size(o, o)).try_collect()?
+    } else {
+        vec![0; oids.len()]
+    };
+
+    summarizer::chunks(&sizes, budget)
+        .into_iter()
+        .map(|chunk| {
+            let (newest, oldest) = (oids[chunk.start], oids[chunk.end - 1]);
+            let files = crate::git::file_diffs(repo, newest, oldest, &exclude)?;
+            let context = PromptContext {
+                commits: commits[chunk.clone()].iter().map(|c| c.message()).collect(),
+                diff: summarizer::budget_diff(&files, budget),
+                diff_stat: crate::git::diff_stat_oids(repo, newest, oldest, &exclude)?,
+                ..context.clone()
+            };
+            Ok(Prompt {
+                text: prompt_text(&commits[chunk], &context)?,
+                commits: Some((newest, oldest)),
+            })
+        })
+        .collect()
 }
 
 fn find_first_version_of_project<T, H>(history: T) -> Option<SemVer>
@@ -165,99 +1004,381 @@ where
     history.map(|c| c.version()).min()
 }
 
-fn get_entries_for_window(
-    messages: &[String],
-    diff: &str,
+fn get_entries_for_window<H: HistoryItem>(
+    window: &Window<H>,
+    repo: Option<&Repository>,
+    samples: &str,
     config: &Config,
-) -> anyhow::Result<Vec<String>> {
-    if let Some(command) = &config.summarizer_command {
-        run_summarizer(command, messages, diff)
-    } else {
-        Ok(messages
-            .iter()
-            .filter_map(|m| conventional_entry(m, config))
-            .collect())
-    }
-}
-
-fn run_summarizer(command: &str, messages: &[String], diff: &str) -> anyhow::Result<Vec<String>> {
-    let prompt = format!(
-        "Generate a concise changelog entry list for the following changes.\n\
-         Output one entry per line, no bullet points or numbering.\n\
-         Only include user-facing changes worth noting in a changelog.\n\
-         \n\
-         ## Commits\n\
-         {}\n\
-         \n\
-         ## Diff\n\
-         {}",
-        messages.join("\n"),
-        diff,
-    );
-
-    eprintln!("Running summarizer: {}", command);
-
-    let mut child = Command::new("sh")
-        .arg("-c")
-        .arg(command)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .map_err(|e| anyhow::anyhow!("Failed to launch summarizer '{}': {}", command, e))?;
-
-    // Ignore broken pipe — if the command exits early due to an error, we'll
-    // catch the real cause via the exit status below.
-    let _ = child.stdin.as_mut().unwrap().write_all(prompt.as_bytes());
-    drop(child.stdin.take());
-
-    let mut entries = Vec::new();
-    let reader = BufReader::new(child.stdout.take().unwrap());
-    for line in reader.lines() {
-        let line = line?;
-        eprintln!("{}", line);
-        if !line.trim().is_empty() {
-            entries.push(line);
+) -> anyhow::Result<Vec<ReleaseEntry>> {
+    if let Some(backend) = config.summarizer_backend() {
+        let before = repo.map(TrackedState::new).transpose()?;
+        let summarized = summarized_entries(window, repo, samples, config, &backend);
+        if let (Some(repo), Some(before)) = (repo, before) {
+            let changes = before.changes(&TrackedState::new(repo)?);
+            if !changes.is_empty() {
+                anyhow::bail!(
+                    "The summarizer changed {} in the repository, so clog stopped. \
+                     Check the changes, and consider `sandbox = true` under [summarizer].",
+                    changes.join(", ")
+                );
+            }
+        }
+        match summarized {
+            std::result::Result::Ok(entries) => return Ok(entries),
+            Err(e) if config.summarizer.fallback == Fallback::Conventional => eprintln!(
+                "{:#}\nListing the commits of version {} instead",
+                e, window.version
+            ),
+            Err(e) => return Err(e),
         }
     }
+    Ok(conventional_entries(&window.commits, config))
+}
 
-    let status = child.wait()?;
-    if !status.success() {
-        anyhow::bail!(
-            "Summarizer '{}' exited with status {}. \
-             Check your summarizer_command in clog.toml.",
-            command,
-            status
-        );
+fn summarized_entries<H: HistoryItem>(
+    window: &Window<H>,
+    repo: Option<&Repository>,
+    samples: &str,
+    config: &Config,
+    backend: &Backend,
+) -> anyhow::Result<Vec<ReleaseEntry>> {
+    let cache = repo
+        .filter(|_| config.summarizer_cache)
+        .map(SummaryCache::new);
+    let mut entries: Vec<ReleaseEntry> = vec![];
+    let mut suggested: Option<SemVerBump> = None;
+    for prompt in summarizer_prompts(window, repo, samples, config)? {
+        let summary = summarizer::summarize(backend, &config.summarizer, &prompt, cache.as_ref())?;
+        suggested = suggested.max(summary.bump);
+        for entry in summary.entries {
+            if entries.iter().any(|e| e.text == entry.text) {
+                continue;
+            }
+            // clean entries are marked when they are rendered
+            let text = if entry.breaking && !config.clean_entries {
+                format!("**Breaking:** {}", entry.text)
+            } else {
+                entry.text
+            };
+            entries.push(ReleaseEntry {
+                breaking: entry.breaking,
+                section: entry.category,
+                ..ReleaseEntry::new(text)
+            });
+        }
+    }
+    if let (None, Some(bump)) = (window.date, suggested) {
+        let version = window.previous_version.bump(bump);
+        if version != window.version {
+            eprintln!(
+                "The summarizer suggests version {} (a {} bump), but the next version is {}",
+                version, bump, window.version
+            );
+        }
     }
-
     Ok(entries)
 }
 
-fn conventional_entry(message: &str, config: &Config) -> Option<String> {
+fn conventional_entries<H: HistoryItem>(window: &[H], config: &Config) -> Vec<ReleaseEntry> {
+    let is_dependency_update = |c: &&H| {
+        config
+            .dependencies
+            .as_ref()
+            .is_some_and(|deps| deps.is_update(&c.message(), &c.authors()))
+    };
+    let mut entries: Vec<ReleaseEntry> = window
+        .iter()
+        .filter(|c| c.kind() != HistoryItemKind::ClogBump)
+        .filter(|c| !is_dependency_update(c))
+        // with the breaking changes block, a breaking commit is only listed there
+        .filter(|c| !config.breaking_changes || breaking_change_note(&c.message()).is_none())
+        .filter_map(|c| {
+            let message = c.message();
+            conventional_entry(&message, config).map(|(text, section)| ReleaseEntry {
+                text,
+                commit: c.commit_id(),
+                breaking: crate::get_bump_from_trailer(&message) == SemVerBump::Major
+                    || BREAKING_CHANGE_FOOTER.is_match(&message),
+                section,
+            })
+        })
+        .collect();
+
+    let updates = dependencies::fold_updates(
+        window
+            .iter()
+            .rev()
+            .filter(is_dependency_update)
+            .map(|c| dependencies::parse_update(&c.message())),
+    );
+    entries.extend(updates.iter().map(|update| ReleaseEntry {
+        section: Some(DEPENDENCIES_HEADING.to_string()),
+        ..ReleaseEntry::new(update.render())
+    }));
+    entries
+}
+
+/// The entry for a commit and the section it goes in. Include rules come
+/// first, so they can put bumping commits in a section too.
+fn conventional_entry(message: &str, config: &Config) -> Option<(String, Option<String>)> {
+    let first_line = message.split('\n').next()?;
+    if let Some(rule) = config
+        .include
+        .iter()
+        .find(|rule| rule.pattern.is_match(first_line))
+    {
+        return Some((first_line.to_string(), rule.section.clone()));
+    }
+
     let bump = crate::get_bump_from_trailer(message);
     if bump != SemVerBump::None {
-        return message.split('\n').next().map(String::from);
+        return Some((first_line.to_string(), None));
     }
 
-    let mut patterns = config
-        .patterns
-        .major
-        .iter()
-        .chain(&config.patterns.minor)
-        .chain(&config.patterns.patch);
-
-    let first_line = message.split('\n').next()?;
-    if patterns.any(|r| r.is_match(first_line)) {
-        Some(first_line.to_string())
+    // the whole message, as footers such as `BREAKING CHANGE:` bump too
+    if crate::get_bump_from_heading(config, message) != SemVerBump::None {
+        Some((first_line.to_string(), None))
     } else {
         None
     }
 }
 
+static CONVENTIONAL_HEADER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<type>[[:alpha:]][\w-]*)(?:\((?P<scope>[^)]*)\))?(?P<breaking>!)?:\s*(?P<description>.*)$")
+        .unwrap()
+});
+
+/// The parts of a conventional commit subject line
+#[derive(Debug, PartialEq, Eq)]
+struct ConventionalHeader<'a> {
+    kind: &'a str,
+    scope: Option<&'a str>,
+    breaking: bool,
+    description: &'a str,
+}
+
+fn parse_conventional_header(line: &str) -> Option<ConventionalHeader<'_>> {
+    let caps = CONVENTIONAL_HEADER.captures(line.trim())?;
+    Some(ConventionalHeader {
+        kind: caps.name("type")?.as_str(),
+        scope: caps.name("scope").map(|m| m.as_str()),
+        breaking: caps.name("breaking").is_some(),
+        description: caps.name("description")?.as_str(),
+    })
+}
+
+static BREAKING_CHANGE_FOOTER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?m)^BREAKING[ -]CHANGE:[ \t]*(?P<text>.*)$").unwrap());
+
+/// A git trailer or conventional commit footer, like `Refs: #123`
+static FOOTER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[\w-]+(: | #)").unwrap());
+
+/// What a commit says about how it breaks things: the `BREAKING CHANGE:`
+/// footer, or else the body of a `!` commit, or else its description.
+fn breaking_change_note(message: &str) -> Option<String> {
+    let (subject, body) = message.split_once('\n').unwrap_or((message, ""));
+
+    if let Some(caps) = BREAKING_CHANGE_FOOTER.captures(body) {
+        // the footer runs on until the next footer
+        let rest = &body[caps.get(0).unwrap().end()..];
+        let continuation = rest
+            .lines()
+            .skip(1)
+            .take_while(|l| !FOOTER.is_match(l) && !BREAKING_CHANGE_FOOTER.is_match(l));
+        let note = std::iter::once(&caps["text"])
+            .chain(continuation)
+            .flat_map(str::split_whitespace)
+            .join(" ");
+        return (!note.is_empty()).then_some(note);
+    }
+
+    let header = parse_conventional_header(subject)?;
+    if !header.breaking {
+        return None;
+    }
+    let prose = body
+        .split("\n\n")
+        .filter(|p| !p.lines().all(|l| l.trim().is_empty() || FOOTER.is_match(l)))
+        .flat_map(str::split_whitespace)
+        .join(" ");
+    if prose.is_empty() {
+        Some(header.description.to_string())
+    } else {
+        Some(prose)
+    }
+}
+
+/// Turn an entry like `feat(api)!: add a thing.` into
+/// `**Breaking:** **api:** Add a thing`. Text which is not a conventional
+/// commit header, such as a trailer-based entry, is only tidied up.
+fn clean_entry_text(text: &str, breaking: bool) -> String {
+    let (scope, description, breaking) = match parse_conventional_header(text) {
+        Some(header) => (
+            header.scope,
+            header.description,
+            breaking || header.breaking,
+        ),
+        None => (None, text.trim(), breaking),
+    };
+
+    let description = match description.strip_suffix('.') {
+        Some(trimmed) if !trimmed.ends_with('.') => trimmed,
+        _ => description,
+    };
+    let mut chars = description.chars();
+    let description = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    };
+
+    let mut cleaned = String::new();
+    if breaking {
+        cleaned.push_str("**Breaking:** ");
+    }
+    if let Some(scope) = scope.filter(|s| !s.is_empty()) {
+        cleaned.push_str(&format!("**{}:** ", scope));
+    }
+    cleaned.push_str(&description);
+    cleaned
+}
+
+mod json {
+    use chrono::TimeZone;
+    use serde::{Deserialize, Serialize};
+
+    use crate::changelog::{parse_conventional_header, Release, ReleaseEntry};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ReleaseRecord {
+        pub version: String,
+        pub date: String,
+        pub previous_version: String,
+        pub bump: String,
+        pub commits: Vec<String>,
+        pub entries: Vec<EntryRecord>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub breaking_changes: Vec<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub contributors: Vec<ContributorRecord>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ContributorRecord {
+        pub name: String,
+        pub email: String,
+        pub first_time: bool,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct EntryRecord {
+        pub text: String,
+        #[serde(rename = "type")]
+        pub kind: Option<String>,
+        pub scope: Option<String>,
+        pub breaking: bool,
+        pub commit: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub section: Option<String>,
+    }
+
+    pub fn record(release: &Release) -> ReleaseRecord {
+        let date = match release.date {
+            Some(t) => chrono::Local.timestamp_opt(t, 0).unwrap(),
+            None => chrono::Local::now(),
+        };
+        ReleaseRecord {
+            version: release.version.to_string(),
+            date: date.format("%Y-%m-%d").to_string(),
+            previous_version: release.previous_version.to_string(),
+            bump: release
+                .version
+                .bump_from(&release.previous_version)
+                .to_string(),
+            commits: release.commits.iter().map(|c| c.to_string()).collect(),
+            entries: release.entries.iter().map(entry_record).collect(),
+            breaking_changes: release
+                .breaking_changes
+                .iter()
+                .map(|e| e.text.clone())
+                .collect(),
+            contributors: release
+                .contributors
+                .iter()
+                .map(|c| ContributorRecord {
+                    name: c.person.name.clone(),
+                    email: c.person.email.clone(),
+                    first_time: c.first_time,
+                })
+                .collect(),
+        }
+    }
+
+    fn entry_record(entry: &ReleaseEntry) -> EntryRecord {
+        let header = parse_conventional_header(&entry.text);
+        EntryRecord {
+            text: entry.text.clone(),
+            kind: header.as_ref().map(|h| h.kind.to_lowercase()),
+            scope: header.as_ref().and_then(|h| h.scope.map(String::from)),
+            breaking: entry.breaking || header.is_some_and(|h| h.breaking),
+            commit: entry.commit.map(|c| c.to_string()),
+            section: entry.section.clone(),
+        }
+    }
+
+    pub fn render(records: &[ReleaseRecord]) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(records)? + "\n")
+    }
+}
+
 mod render {
-    use crate::{changelog::ChangeLogEntry, Config};
+    use crate::{
+        changelog::{parse, ChangeLogEntry},
+        ChangelogFormat, Config,
+    };
+
+    pub fn render_changelog(changelog_entries: &[ChangeLogEntry], config: &Config) -> String {
+        match config.changelog_format {
+            ChangelogFormat::Clog => render_clog(changelog_entries),
+            ChangelogFormat::KeepAChangelog => format!(
+                "{}{}",
+                KEEP_A_CHANGELOG_HEADER,
+                render_keep_a_changelog(changelog_entries)
+            ),
+        }
+    }
+
+    /// Render releases without anything which only belongs at the top of the file
+    pub fn render_releases(changelog_entries: &[ChangeLogEntry], config: &Config) -> String {
+        match config.changelog_format {
+            ChangelogFormat::Clog => render_clog(changelog_entries),
+            ChangelogFormat::KeepAChangelog => render_keep_a_changelog(changelog_entries),
+        }
+    }
+
+    /// Add rendered releases above the releases already in a changelog
+    pub fn insert_releases(
+        new_changelog: &str,
+        original_changelog: &str,
+        config: &Config,
+    ) -> String {
+        match config.changelog_format {
+            ChangelogFormat::Clog => format!("{new_changelog}{original_changelog}"),
+            ChangelogFormat::KeepAChangelog => {
+                // new releases go below the title and any unreleased notes
+                let lines: Vec<&str> = original_changelog.split_inclusive('\n').collect();
+                let at = parse::first_release_line(original_changelog).unwrap_or(lines.len());
+                let mut changelog = lines[..at].concat();
+                if !changelog.is_empty() && !changelog.ends_with("\n\n") {
+                    changelog.push('\n');
+                }
+                changelog.push_str(new_changelog);
+                changelog.push_str(&lines[at..].concat());
+                changelog
+            }
+        }
+    }
 
-    pub fn render_changelog(changelog_entries: &[ChangeLogEntry], _config: &Config) -> String {
+    fn render_clog(changelog_entries: &[ChangeLogEntry]) -> String {
         let mut changelog = String::new();
         for entry in changelog_entries {
             match entry {
@@ -267,6 +1388,10 @@ mod render {
                 ChangeLogEntry::InitialVersion(sem_ver) => {
                     changelog.push_str(&format!("# Version {}\n- Initial Commit", sem_ver));
                 }
+                ChangeLogEntry::ReleaseDate(_) => continue,
+                ChangeLogEntry::Category(name) => {
+                    changelog.push_str(&format!("## {}", name));
+                }
                 ChangeLogEntry::Entry(msg) => {
                     changelog.push_str(&format!("- {}", msg));
                 }
@@ -276,13 +1401,45 @@ mod render {
         changelog
     }
 
-    pub fn prepend_render_changelog(
-        changelog_entries: &[ChangeLogEntry],
-        original_changelog: &str,
-        config: &Config,
-    ) -> String {
-        let new_changelog = render_changelog(changelog_entries, config);
-        format!("{new_changelog}{original_changelog}")
+    const KEEP_A_CHANGELOG_HEADER: &str = "# Changelog\n\n\
+        All notable changes to this project will be documented in this file.\n\n\
+        The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/),\n\
+        and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).\n\n";
+
+    fn render_keep_a_changelog(changelog_entries: &[ChangeLogEntry]) -> String {
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let mut changelog = String::new();
+        for (i, entry) in changelog_entries.iter().enumerate() {
+            match entry {
+                ChangeLogEntry::BumpVersion(sem_ver) => {
+                    if i > 0 {
+                        changelog.push('\n');
+                    }
+                    let date = match changelog_entries.get(i + 1) {
+                        Some(ChangeLogEntry::ReleaseDate(date)) => date,
+                        _ => &today,
+                    };
+                    changelog.push_str(&format!("## [{}] - {}\n", sem_ver, date));
+                }
+                ChangeLogEntry::InitialVersion(sem_ver) => {
+                    if i > 0 {
+                        changelog.push('\n');
+                    }
+                    changelog.push_str(&format!("## [{}]\n- Initial Commit\n", sem_ver));
+                }
+                ChangeLogEntry::ReleaseDate(_) => {}
+                ChangeLogEntry::Category(name) => {
+                    changelog.push_str(&format!("### {}\n", name));
+                }
+                ChangeLogEntry::Entry(msg) => {
+                    changelog.push_str(&format!("- {}\n", msg));
+                }
+            }
+        }
+        if !changelog.is_empty() {
+            changelog.push('\n');
+        }
+        changelog
     }
 }
 
@@ -290,12 +1447,17 @@ mod render {
 mod test {
 
     use crate::{
-        changelog::{get_all_changelog_entries, ChangeLogEntry},
+        changelog::{
+            breaking_change_note, clean_entry_text, get_all_changelog_entries, get_all_releases,
+            json, render, ChangeLogEntry,
+        },
         semver::SemVer,
         test_support::TestCommitWrapper,
-        Config,
+        ChangelogFormat, Config, HistoryItemKind, IncludeRule,
     };
 
+    use regex::Regex;
+
     use rstest::rstest;
 
     #[rstest]
@@ -375,4 +1537,259 @@ mod test {
         let changelog = get_all_changelog_entries(history.into_iter(), None, &config).unwrap();
         assert_eq!(expected, changelog);
     }
+
+    #[test]
+    fn test_breaking_changes_block() {
+        let history = vec![
+            TestCommitWrapper::new_normal("fix: a bug", SemVer::new(1, 5, 0, None, None)),
+            TestCommitWrapper::new_normal(
+                "feat: new config\n\nBREAKING CHANGE: the `path` key is now `dir`",
+                SemVer::new(1, 5, 0, None, None),
+            ),
+            TestCommitWrapper::new_normal("feat(api)!: drop v1", SemVer::new(1, 5, 0, None, None)),
+        ];
+        let config = Config {
+            breaking_changes: true,
+            ..Config::default()
+        };
+        let changelog = get_all_changelog_entries(history.into_iter(), None, &config).unwrap();
+        assert_eq!(
+            changelog,
+            vec![
+                ChangeLogEntry::BumpVersion(SemVer::new(2, 0, 0, None, None)),
+                ChangeLogEntry::Category("Breaking changes".to_string()),
+                ChangeLogEntry::Entry("the `path` key is now `dir`".to_string()),
+                ChangeLogEntry::Entry("drop v1".to_string()),
+                ChangeLogEntry::Category("Changes".to_string()),
+                ChangeLogEntry::Entry("fix: a bug".to_string()),
+                ChangeLogEntry::InitialVersion(SemVer::new(1, 5, 0, None, None)),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::plain("feat: add a thing", false, "Add a thing")]
+    #[case::scope(
+        "fix(parser): handle empty input.",
+        false,
+        "**parser:** Handle empty input"
+    )]
+    #[case::breaking("feat(api)!: drop v1", false, "**Breaking:** **api:** Drop v1")]
+    #[case::ellipsis("feat: more to come...", false, "More to come...")]
+    #[case::not_conventional("tidy up the docs.", false, "Tidy up the docs")]
+    #[case::breaking_trailer("rework config", true, "**Breaking:** Rework config")]
+    fn test_clean_entry_text(#[case] text: &str, #[case] breaking: bool, #[case] expected: &str) {
+        assert_eq!(clean_entry_text(text, breaking), expected);
+    }
+
+    #[rstest]
+    #[case::footer(
+        "feat: new config\n\nSome context.\n\nBREAKING CHANGE: the `path` key\nis now `dir`.\nRefs: #12",
+        Some("the `path` key is now `dir`.")
+    )]
+    #[case::hyphenated_footer(
+        "fix: x\n\nBREAKING-CHANGE: old flags are gone",
+        Some("old flags are gone")
+    )]
+    #[case::bang_body(
+        "feat(api)!: drop v1\n\nMove to the v2 endpoints,\nsee the docs.\n\nRefs: #3",
+        Some("Move to the v2 endpoints, see the docs.")
+    )]
+    #[case::bang_without_body("feat(api)!: drop v1", Some("drop v1"))]
+    #[case::not_breaking("feat: add a thing\n\nWith a body.", None)]
+    fn test_breaking_change_note(#[case] message: &str, #[case] expected: Option<&str>) {
+        assert_eq!(breaking_change_note(message).as_deref(), expected);
+    }
+
+    #[test]
+    fn test_clean_trailer_entries() {
+        let history = vec![
+            TestCommitWrapper::new_normal(
+                &format!("rework config\n{}: major", crate::CLOG_BUMP_TRAILER),
+                SemVer::new(1, 5, 0, None, None),
+            ),
+            TestCommitWrapper::new_normal("fix: an old bug.", SemVer::new(1, 5, 0, None, None)),
+        ];
+        let config = Config {
+            clean_entries: true,
+            ..Default::default()
+        };
+        let releases = get_all_releases(history.into_iter(), None, &[], "", &config).unwrap();
+        let entries: Vec<_> = releases[0]
+            .changelog_entries(&config)
+            .filter(|e| matches!(e, ChangeLogEntry::Entry(_)))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ChangeLogEntry::Entry("**Breaking:** Rework config".to_string()),
+                ChangeLogEntry::Entry("An old bug".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_include_rules() {
+        let history = vec![
+            TestCommitWrapper::new_normal("docs: explain config", SemVer::new(0, 1, 0, None, None)),
+            TestCommitWrapper::new_normal("perf: faster parsing", SemVer::new(0, 1, 0, None, None)),
+            TestCommitWrapper::new_normal("chore: tidy up", SemVer::new(0, 1, 0, None, None)),
+            TestCommitWrapper::new_normal("feat: a thing", SemVer::new(0, 1, 0, None, None)),
+            TestCommitWrapper::new_normal("security: patch", SemVer::new(0, 1, 0, None, None)),
+        ];
+        let rule = |pattern: &str, section: Option<&str>| IncludeRule {
+            pattern: Regex::new(pattern).unwrap(),
+            section: section.map(String::from),
+        };
+        let config = Config {
+            include: vec![
+                rule("^perf:", Some("Performance")),
+                rule("^docs:", Some("Documentation")),
+                rule("^security:", None),
+            ],
+            ..Default::default()
+        };
+        let changelog = get_all_changelog_entries(history.into_iter(), None, &config).unwrap();
+        assert_eq!(
+            changelog,
+            vec![
+                ChangeLogEntry::BumpVersion(SemVer::new(0, 2, 0, None, None)),
+                ChangeLogEntry::Entry("feat: a thing".to_string()),
+                ChangeLogEntry::Entry("security: patch".to_string()),
+                ChangeLogEntry::Category("Performance".to_string()),
+                ChangeLogEntry::Entry("perf: faster parsing".to_string()),
+                ChangeLogEntry::Category("Documentation".to_string()),
+                ChangeLogEntry::Entry("docs: explain config".to_string()),
+                ChangeLogEntry::InitialVersion(SemVer::new(0, 1, 0, None, None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dependency_updates() {
+        let history = vec![
+            TestCommitWrapper::new_normal(
+                "Bump serde from 1.0.2 to 1.0.3",
+                SemVer::new(0, 1, 0, None, None),
+            )
+            .with_author("dependabot[bot]", "support@github.com"),
+            TestCommitWrapper::new_normal("feat: a thing", SemVer::new(0, 1, 0, None, None)),
+            TestCommitWrapper::new_normal(
+                "chore(deps): update rust crate toml to v0.9.8",
+                SemVer::new(0, 1, 0, None, None),
+            ),
+            TestCommitWrapper::new_normal(
+                "Bump serde from 1.0.1 to 1.0.2",
+                SemVer::new(0, 1, 0, None, None),
+            )
+            .with_author("dependabot[bot]", "support@github.com"),
+        ];
+        let config = Config {
+            dependencies: Some(Default::default()),
+            clean_entries: true,
+            ..Default::default()
+        };
+        let changelog = get_all_changelog_entries(history.into_iter(), None, &config).unwrap();
+        assert_eq!(
+            changelog,
+            vec![
+                ChangeLogEntry::BumpVersion(SemVer::new(0, 2, 0, None, None)),
+                ChangeLogEntry::Entry("A thing".to_string()),
+                ChangeLogEntry::Category("Dependencies".to_string()),
+                ChangeLogEntry::Entry("serde 1.0.1 → 1.0.3".to_string()),
+                ChangeLogEntry::Entry("toml → 0.9.8".to_string()),
+                ChangeLogEntry::InitialVersion(SemVer::new(0, 1, 0, None, None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_release_records() {
+        let history = vec![
+            TestCommitWrapper::new_normal("feat(api)!: new api", SemVer::new(0, 2, 0, None, None)),
+            TestCommitWrapper::new_normal("fix: test 2", SemVer::new(0, 1, 0, None, None)),
+        ];
+        let config = Config::default();
+        let releases = get_all_releases(history.into_iter(), None, &[], "", &config).unwrap();
+        let records: Vec<_> = releases.iter().map(json::record).collect();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].version, "0.3.0");
+        assert_eq!(records[0].previous_version, "0.2.0");
+        assert_eq!(records[0].bump, "minor");
+        assert_eq!(records[0].entries[0].kind.as_deref(), Some("feat"));
+        assert_eq!(records[0].entries[0].scope.as_deref(), Some("api"));
+        assert!(records[0].entries[0].breaking);
+        assert_eq!(records[1].version, "0.2.0");
+        assert_eq!(records[1].previous_version, "0.1.0");
+        assert_eq!(records[1].entries[0].text, "fix: test 2");
+        assert!(!records[1].entries[0].breaking);
+    }
+
+    #[test]
+    fn test_contributors() {
+        let history = vec![
+            TestCommitWrapper::new_normal("feat: b", SemVer::new(0, 2, 0, None, None))
+                .with_author("Bob", "bob@example.com")
+                .with_author("Carol", "carol@example.com"),
+            TestCommitWrapper::new_normal("feat: a", SemVer::new(0, 2, 0, None, None))
+                .with_author("Alice", "ALICE@example.com"),
+            TestCommitWrapper::new(
+                "chore: bump version",
+                SemVer::new(0, 2, 0, None, None),
+                HistoryItemKind::ClogBump,
+            )
+            .with_author("Release Bot", "bot@example.com"),
+            TestCommitWrapper::new_normal("feat: c", SemVer::new(0, 1, 0, None, None))
+                .with_author("Alice", "alice@example.com"),
+        ];
+        let config = Config {
+            contributors: true,
+            ..Default::default()
+        };
+        let changelog = get_all_changelog_entries(history.into_iter(), None, &config).unwrap();
+        assert_eq!(
+            changelog,
+            vec![
+                ChangeLogEntry::BumpVersion(SemVer::new(0, 3, 0, None, None)),
+                ChangeLogEntry::Entry("feat: b".to_string()),
+                ChangeLogEntry::Entry("feat: a".to_string()),
+                ChangeLogEntry::Category("Contributors".to_string()),
+                ChangeLogEntry::Entry("Alice".to_string()),
+                ChangeLogEntry::Entry("Bob (first contribution)".to_string()),
+                ChangeLogEntry::Entry("Carol (first contribution)".to_string()),
+                ChangeLogEntry::BumpVersion(SemVer::new(0, 2, 0, None, None)),
+                ChangeLogEntry::Entry("feat: c".to_string()),
+                ChangeLogEntry::Category("Contributors".to_string()),
+                ChangeLogEntry::Entry("Alice (first contribution)".to_string()),
+                ChangeLogEntry::InitialVersion(SemVer::new(0, 1, 0, None, None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prepend_keep_a_changelog() {
+        let config = Config {
+            changelog_format: ChangelogFormat::KeepAChangelog,
+            ..Default::default()
+        };
+        let original =
+            "# Changelog\n\n## [Unreleased]\n- Soon\n\n## [1.0.0] - 2026-01-02\n- First\n";
+        let entries = vec![
+            ChangeLogEntry::BumpVersion(SemVer::new(1, 1, 0, None, None)),
+            ChangeLogEntry::ReleaseDate("2026-10-17".to_string()),
+            ChangeLogEntry::Category("Added".to_string()),
+            ChangeLogEntry::Entry("A feature".to_string()),
+        ];
+        assert_eq!(
+            render::insert_releases(
+                &render::render_releases(&entries, &config),
+                original,
+                &config
+            ),
+            "# Changelog\n\n## [Unreleased]\n- Soon\n\n\
+             ## [1.1.0] - 2026-10-17\n### Added\n- A feature\n\n\
+             ## [1.0.0] - 2026-01-02\n- First\n"
+        );
+    }
 }
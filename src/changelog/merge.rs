@@ -0,0 +1,544 @@
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{
+    changelog::{parse, render, ChangeLogEntry},
+    semver::SemVer,
+    ChangelogFormat, Config,
+};
+
+/// A link reference definition, like `[1.1.0]: https://...`
+static LINK_REFERENCE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\[(?P<label>[^\]]+)\]:\s*\S").unwrap());
+
+/// One version's section of a changelog
+struct Section {
+    version: SemVer,
+    initial: bool,
+    date: Option<String>,
+    /// Entries under each heading, `None` before the first heading
+    groups: Vec<(Option<String>, Vec<String>)>,
+}
+
+impl Section {
+    fn contains(&self, entry: &str) -> bool {
+        self.groups
+            .iter()
+            .any(|(_, e)| e.iter().any(|e| e == entry))
+    }
+
+    fn add(&mut self, category: Option<&String>, entry: String) {
+        if self.contains(&entry) {
+            return;
+        }
+        match self.groups.iter_mut().find(|(c, _)| c.as_ref() == category) {
+            Some((_, entries)) => entries.push(entry),
+            None => self.groups.push((category.cloned(), vec![entry])),
+        }
+    }
+
+    /// Add the date and entries of the same release from the other side
+    fn merge(&mut self, theirs: &Section) {
+        self.date = self.date.take().or(theirs.date.clone());
+        for (category, entries) in &theirs.groups {
+            for entry in entries {
+                self.add(category.as_ref(), entry.clone());
+            }
+        }
+    }
+
+    /// Leave out the entries of `base` which are missing from either side,
+    /// as that side removed or reworded them
+    fn drop_removed(&mut self, base: &Section, ours: &Section, theirs: &Section) {
+        let removed: HashSet<&String> = base
+            .groups
+            .iter()
+            .flat_map(|(_, entries)| entries)
+            .filter(|e| !ours.contains(e) || !theirs.contains(e))
+            .collect();
+        for (_, entries) in &mut self.groups {
+            entries.retain(|e| !removed.contains(e));
+        }
+        self.groups.retain(|(_, entries)| !entries.is_empty());
+    }
+
+    fn entries(&self) -> Vec<ChangeLogEntry> {
+        let heading = match self.initial {
+            true => ChangeLogEntry::InitialVersion(self.version.clone()),
+            false => ChangeLogEntry::BumpVersion(self.version.clone()),
+        };
+        let mut entries = vec![heading];
+        entries.extend(self.date.clone().map(ChangeLogEntry::ReleaseDate));
+        for (category, group) in &self.groups {
+            entries.extend(category.clone().map(ChangeLogEntry::Category));
+            entries.extend(group.iter().cloned().map(ChangeLogEntry::Entry));
+        }
+        entries
+    }
+}
+
+fn sections(entries: Vec<ChangeLogEntry>) -> Vec<Section> {
+    let mut sections: Vec<Section> = vec![];
+    let mut category = None;
+    for entry in entries {
+        let section = |version, initial| Section {
+            version,
+            initial,
+            date: None,
+            groups: vec![],
+        };
+        match (entry, sections.last_mut()) {
+            (ChangeLogEntry::BumpVersion(v), _) => {
+                sections.push(section(v, false));
+                category = None;
+            }
+            (ChangeLogEntry::InitialVersion(v), _) => {
+                sections.push(section(v, true));
+                category = None;
+            }
+            (ChangeLogEntry::ReleaseDate(date), Some(last)) => last.date = Some(date),
+            (ChangeLogEntry::Category(name), Some(_)) => category = Some(name),
+            (ChangeLogEntry::Entry(text), Some(last)) => last.add(category.as_ref(), text),
+            (_, None) => {}
+        }
+    }
+    sections
+}
+
+/// A changelog split into the text above the first release, the text of
+/// each release, and the link references at the end
+struct Document {
+    header: String,
+    releases: Vec<(SemVer, String)>,
+    trailer: Vec<String>,
+}
+
+impl Document {
+    fn parse(text: &str) -> Self {
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let at = parse::first_release_line(text).unwrap_or(lines.len());
+        // the link references below the last release, without the blank
+        // lines which set them apart
+        let mut end = lines.len();
+        while end > at
+            && (lines[end - 1].trim().is_empty() || LINK_REFERENCE.is_match(lines[end - 1]))
+        {
+            end -= 1;
+        }
+        let trailer_at = end
+            + lines[end..]
+                .iter()
+                .take_while(|l| l.trim().is_empty())
+                .count();
+
+        let mut releases: Vec<(SemVer, String)> = vec![];
+        for line in &lines[at..trailer_at] {
+            match parse::parse_version_heading(line.trim_end()) {
+                Some((version, _)) => releases.push((version, line.to_string())),
+                None => releases.last_mut().unwrap().1.push_str(line),
+            }
+        }
+        Self {
+            header: lines[..at].concat(),
+            releases,
+            trailer: lines[trailer_at..]
+                .iter()
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| with_newline(l))
+                .collect(),
+        }
+    }
+
+    fn release(&self, version: &SemVer) -> Option<&str> {
+        self.releases
+            .iter()
+            .find(|(v, _)| v == version)
+            .map(|(_, text)| text.as_str())
+    }
+}
+
+/// A changelog merged from two sides
+pub struct Merged {
+    pub text: String,
+    /// How many parts could not be merged, and have conflict markers
+    pub conflicts: usize,
+}
+
+/// Merge two changelogs version by version, keeping every entry from both
+/// sides once. With the common ancestor of both as `base`, what one side
+/// removed or reworded since then is left out, as is a part which only one
+/// side changed. Text which is not an entry, like prose in a release or the
+/// header, is kept when one side has all of the other's. Where neither
+/// does, both sides are left between conflict markers.
+pub fn merge_changelogs(base: Option<&str>, ours: &str, theirs: &str, config: &Config) -> Merged {
+    let base = base.map(Document::parse);
+    let (ours, theirs) = (Document::parse(ours), Document::parse(theirs));
+    let mut conflicts = 0;
+    let mut resolve = |merged: Option<String>, ours: &str, theirs: &str| {
+        merged.unwrap_or_else(|| {
+            conflicts += 1;
+            format!(
+                "<<<<<<< ours\n{}=======\n{}>>>>>>> theirs\n",
+                with_newline(ours),
+                with_newline(theirs)
+            )
+        })
+    };
+
+    let header = resolve(
+        merge_text(
+            base.as_ref().map(|b| b.header.as_str()),
+            &ours.header,
+            &theirs.header,
+        ),
+        &ours.header,
+        &theirs.header,
+    );
+    let versions = ours
+        .releases
+        .iter()
+        .chain(&theirs.releases)
+        .map(|(v, _)| v.clone())
+        .sorted_by(|a, b| b.cmp(a))
+        .dedup();
+    let releases: Vec<String> = versions
+        .filter_map(|v| {
+            let base = base.as_ref().and_then(|b| b.release(&v));
+            match (ours.release(&v), theirs.release(&v)) {
+                (Some(o), Some(t)) => Some(resolve(merge_release(base, o, t, config), o, t)),
+                // the other side removed the release, and this one left it alone
+                (Some(text), None) | (None, Some(text)) if base.is_some_and(|b| same(b, text)) => {
+                    None
+                }
+                (Some(text), None) | (None, Some(text)) => Some(text.to_string()),
+                (None, None) => unreachable!("the version comes from one of the sides"),
+            }
+        })
+        .collect();
+    let trailer = resolve(
+        merge_link_references(
+            base.as_ref().map(|b| b.trailer.as_slice()),
+            &ours.trailer,
+            &theirs.trailer,
+        ),
+        &ours.trailer.concat(),
+        &theirs.trailer.concat(),
+    );
+
+    let mut text = header;
+    let count = releases.len();
+    for (i, release) in releases.into_iter().enumerate() {
+        if i + 1 < count {
+            text.push_str(&separated(release, config.changelog_format));
+        } else if !trailer.is_empty() {
+            text.push_str(&separated(release, ChangelogFormat::KeepAChangelog));
+        } else {
+            text.push_str(&release);
+        }
+    }
+    text.push_str(&trailer);
+    Merged { text, conflicts }
+}
+
+/// Whether two parts of a changelog read the same, but for the blank lines
+/// after them
+fn same(a: &str, b: &str) -> bool {
+    a.trim_end() == b.trim_end()
+}
+
+/// The side which changed since `base`, if only one did, or else the side
+/// with every non-blank line of the other
+fn merge_text(base: Option<&str>, ours: &str, theirs: &str) -> Option<String> {
+    match base {
+        _ if same(ours, theirs) => Some(ours.to_string()),
+        Some(base) if same(base, ours) => Some(theirs.to_string()),
+        Some(base) if same(base, theirs) => Some(ours.to_string()),
+        _ => covering(ours, theirs).map(String::from),
+    }
+}
+
+/// The side with every non-blank line of the other, if there is one
+fn covering<'a>(ours: &'a str, theirs: &'a str) -> Option<&'a str> {
+    let lines = |text: &'a str| -> HashSet<&'a str> {
+        text.lines()
+            .map(str::trim_end)
+            .filter(|l| !l.is_empty())
+            .collect()
+    };
+    let (our_lines, their_lines) = (lines(ours), lines(theirs));
+    if their_lines.is_subset(&our_lines) {
+        Some(ours)
+    } else if our_lines.is_subset(&their_lines) {
+        Some(theirs)
+    } else {
+        None
+    }
+}
+
+/// Both sides of a release, with the entries of each but those removed
+/// since `base`, or `None` if they have something else which rendering the
+/// release again would lose
+fn merge_release(base: Option<&str>, ours: &str, theirs: &str, config: &Config) -> Option<String> {
+    let one_side_changed = base.is_some_and(|b| same(b, ours) || same(b, theirs));
+    if one_side_changed || !ours.lines().chain(theirs.lines()).all(parse::is_structured) {
+        return merge_text(base, ours, theirs);
+    }
+    // with a base, the side with all of the other's lines may still have
+    // entries the other removed, so only the two-way merge takes it
+    if let (None, Some(text)) = (base, covering(ours, theirs)) {
+        return Some(text.to_string());
+    }
+    // each text is a single release
+    let section = |text: &str| sections(parse::parse_changelog(text)).pop();
+    let (our_section, their_section) = (section(ours)?, section(theirs)?);
+    let mut merged = section(ours)?;
+    merged.merge(&their_section);
+    if let Some(base) = base.and_then(section) {
+        merged.drop_removed(&base, &our_section, &their_section);
+    }
+    Some(render::render_releases(&merged.entries(), config))
+}
+
+/// Both sides' link references but those one side removed since `base`,
+/// or `None` if both sides changed where a label points
+fn merge_link_references(
+    base: Option<&[String]>,
+    ours: &[String],
+    theirs: &[String],
+) -> Option<String> {
+    let label = |line: &str| {
+        LINK_REFERENCE
+            .captures(line)
+            .map(|caps| caps["label"].to_lowercase())
+    };
+    let find = |lines: Option<&[String]>, line: &str| -> Option<String> {
+        lines?.iter().find(|l| label(l) == label(line)).cloned()
+    };
+    let labels = ours
+        .iter()
+        .chain(theirs.iter().filter(|t| find(Some(ours), t).is_none()));
+    let mut merged = vec![];
+    for line in labels {
+        let (o, t) = (find(Some(ours), line), find(Some(theirs), line));
+        let base = find(base, line);
+        let kept = match (o, t) {
+            (Some(o), Some(t)) => merge_text(base.as_deref(), &o, &t)?,
+            // the other side removed it
+            (Some(line), None) | (None, Some(line))
+                if base.as_deref().is_some_and(|b| same(b, &line)) =>
+            {
+                continue
+            }
+            (Some(line), None) | (None, Some(line)) => line,
+            (None, None) => unreachable!("the label comes from one of the sides"),
+        };
+        merged.push(kept);
+    }
+    Some(merged.concat())
+}
+
+fn with_newline(text: &str) -> String {
+    match text.is_empty() || text.ends_with('\n') {
+        true => text.to_string(),
+        false => format!("{text}\n"),
+    }
+}
+
+/// A release as it should end before the next one: Keep a Changelog sets
+/// them apart with a blank line
+fn separated(release: String, format: ChangelogFormat) -> String {
+    let mut release = with_newline(&release);
+    if format == ChangelogFormat::KeepAChangelog && !release.ends_with("\n\n") {
+        release.push('\n');
+    }
+    release
+}
+
+/// Both sides of a file with conflict markers, or `None` when it has none
+pub fn split_conflict(text: &str) -> Option<(String, String)> {
+    #[derive(PartialEq)]
+    enum Side {
+        Both,
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let mut side = Side::Both;
+    let mut found = false;
+    let (mut ours, mut theirs) = (String::new(), String::new());
+    for line in text.split_inclusive('\n') {
+        match line {
+            l if l.starts_with("<<<<<<<") => {
+                side = Side::Ours;
+                found = true;
+            }
+            // the common ancestor, in the diff3 conflict style
+            l if l.starts_with("|||||||") && side == Side::Ours => side = Side::Base,
+            l if l.starts_with("=======") && side != Side::Both => side = Side::Theirs,
+            l if l.starts_with(">>>>>>>") && side == Side::Theirs => side = Side::Both,
+            l => match side {
+                Side::Both => {
+                    ours.push_str(l);
+                    theirs.push_str(l);
+                }
+                Side::Ours => ours.push_str(l),
+                Side::Base => {}
+                Side::Theirs => theirs.push_str(l),
+            },
+        }
+    }
+    found.then_some((ours, theirs))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_conflict() {
+        let text = "# Version 0.3.0\n<<<<<<< HEAD\n- feat: ours\n||||||| base\n- feat: old\n\
+                    =======\n- feat: theirs\n>>>>>>> branch\n# Version 0.2.0\n- fix: bug\n";
+        let (ours, theirs) = split_conflict(text).unwrap();
+        assert_eq!(
+            ours,
+            "# Version 0.3.0\n- feat: ours\n# Version 0.2.0\n- fix: bug\n"
+        );
+        assert_eq!(
+            theirs,
+            "# Version 0.3.0\n- feat: theirs\n# Version 0.2.0\n- fix: bug\n"
+        );
+        assert_eq!(split_conflict("# Version 0.2.0\n- fix: bug\n"), None);
+    }
+
+    #[test]
+    fn test_merge_by_version() {
+        let ours = "# Version 0.3.0\n- feat: ours\n## Dependencies\n- serde 1 → 2\n\
+                    # Version 0.2.0\n- fix: bug\n# Version 0.1.0\n- Initial Commit\n";
+        let theirs = "# Version 0.2.1\n- fix: theirs\n# Version 0.2.0\n- fix: bug\n- fix: other\n\
+                      # Version 0.1.0\n- Initial Commit\n";
+        assert_eq!(
+            merge_changelogs(None, ours, theirs, &Config::default()).text,
+            "# Version 0.3.0\n- feat: ours\n## Dependencies\n- serde 1 → 2\n\
+             # Version 0.2.1\n- fix: theirs\n# Version 0.2.0\n- fix: bug\n- fix: other\n\
+             # Version 0.1.0\n- Initial Commit\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_header() {
+        let config = Config {
+            changelog_format: ChangelogFormat::KeepAChangelog,
+            ..Default::default()
+        };
+        let ours = "# Changelog\n\n## [Unreleased]\n- Soon\n\n\
+                    ## [1.1.0] - 2026-10-01\n### Added\n- A\n";
+        let theirs = "# Changelog\n\n## [1.0.1] - 2026-09-01\n### Fixed\n- B\n\n\
+                      ## [1.1.0] - 2026-10-01\n### Added\n- A\n- C\n";
+        assert_eq!(
+            merge_changelogs(None, ours, theirs, &config).text,
+            "# Changelog\n\n## [Unreleased]\n- Soon\n\n\
+             ## [1.1.0] - 2026-10-01\n### Added\n- A\n- C\n\n\
+             ## [1.0.1] - 2026-09-01\n### Fixed\n- B\n\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_entries_of_a_release() {
+        let ours = "# Version 0.2.0\n- fix: a\n- fix: b\n# Version 0.1.0\n- Initial Commit\n";
+        let theirs = "# Version 0.2.0\n- fix: a\n- fix: c\n# Version 0.1.0\n- Initial Commit\n";
+        let merged = merge_changelogs(None, ours, theirs, &Config::default());
+        assert_eq!(
+            merged.text,
+            "# Version 0.2.0\n- fix: a\n- fix: b\n- fix: c\n# Version 0.1.0\n- Initial Commit\n"
+        );
+        assert_eq!(merged.conflicts, 0);
+    }
+
+    #[test]
+    fn test_merge_keeps_prose_and_link_references() {
+        let config = Config {
+            changelog_format: ChangelogFormat::KeepAChangelog,
+            ..Default::default()
+        };
+        let ours = "# Changelog\n\n## [1.1.0] - 2026-10-01\n\
+                    Upgrading needs a config change.\n\n### Added\n- A\n\n\
+                    ## [1.0.0] - 2026-09-01\n- First\n\n\
+                    [1.1.0]: https://example.com/compare/v1.0.0...v1.1.0\n\
+                    [1.0.0]: https://example.com/releases/v1.0.0\n";
+        let theirs = "# Changelog\n\n## [1.0.1] - 2026-09-15\n### Fixed\n- B\n\n\
+                      ## [1.0.0] - 2026-09-01\n- First\n\n\
+                      [1.0.1]: https://example.com/compare/v1.0.0...v1.0.1\n\
+                      [1.0.0]: https://example.com/releases/v1.0.0\n";
+        let merged = merge_changelogs(None, ours, theirs, &config);
+        assert_eq!(
+            merged.text,
+            "# Changelog\n\n## [1.1.0] - 2026-10-01\n\
+             Upgrading needs a config change.\n\n### Added\n- A\n\n\
+             ## [1.0.1] - 2026-09-15\n### Fixed\n- B\n\n\
+             ## [1.0.0] - 2026-09-01\n- First\n\n\
+             [1.1.0]: https://example.com/compare/v1.0.0...v1.1.0\n\
+             [1.0.0]: https://example.com/releases/v1.0.0\n\
+             [1.0.1]: https://example.com/compare/v1.0.0...v1.0.1\n"
+        );
+        assert_eq!(merged.conflicts, 0);
+    }
+
+    #[test]
+    fn test_merge_with_base() {
+        let config = Config {
+            changelog_format: ChangelogFormat::KeepAChangelog,
+            ..Default::default()
+        };
+        let base = "# Changelog\n\n## [1.1.0] - 2026-10-01\n- A\n- Typo\n- Gone\n\n\
+                    ## [1.0.1]\n- Fix\n\n## [1.0.0]\n- First\n\n\
+                    [1.1.0]: https://example.com/1.1.0\n\
+                    [1.0.1]: https://example.com/1.0.1\n";
+        // ours rewords an entry and drops 1.0.1, theirs removes one and adds one
+        let ours = "# Changelog\n\n## [1.1.0] - 2026-10-01\n- A\n- Fixed\n- Gone\n\n\
+                    ## [1.0.0]\n- First\n\n\
+                    [1.1.0]: https://example.com/1.1.0\n";
+        let theirs = "# Changelog\n\n## [1.1.0] - 2026-10-01\n- A\n- Typo\n- B\n\n\
+                      ## [1.0.1]\n- Fix\n\n## [1.0.0]\n- First\n\n\
+                      [1.1.0]: https://example.com/1.1.0\n\
+                      [1.0.1]: https://example.com/1.0.1\n";
+        let merged = merge_changelogs(Some(base), ours, theirs, &config);
+        assert_eq!(
+            merged.text,
+            "# Changelog\n\n## [1.1.0] - 2026-10-01\n- A\n- Fixed\n- B\n\n\
+             ## [1.0.0]\n- First\n\n\
+             [1.1.0]: https://example.com/1.1.0\n"
+        );
+        assert_eq!(merged.conflicts, 0);
+
+        // without the base, both sides are kept
+        let merged = merge_changelogs(None, ours, theirs, &config);
+        assert!(merged.text.contains("- Typo\n"));
+        assert!(merged.text.contains("## [1.0.1]\n"));
+    }
+
+    #[test]
+    fn test_merge_leaves_conflicts() {
+        let config = Config {
+            changelog_format: ChangelogFormat::KeepAChangelog,
+            ..Default::default()
+        };
+        let ours = "## [1.1.0]\nOur notes.\n- A\n\n## [1.0.0]\n- First\n\n\
+                    [1.1.0]: https://example.com/ours\n";
+        let theirs = "## [1.1.0]\nTheir notes.\n- A\n\n## [1.0.0]\n- First\n- Second\n\n\
+                      [1.1.0]: https://example.com/theirs\n";
+        let merged = merge_changelogs(None, ours, theirs, &config);
+        assert_eq!(
+            merged.text,
+            "<<<<<<< ours\n## [1.1.0]\nOur notes.\n- A\n\n=======\n\
+             ## [1.1.0]\nTheir notes.\n- A\n\n>>>>>>> theirs\n\n\
+             ## [1.0.0]\n- First\n- Second\n\n\
+             <<<<<<< ours\n[1.1.0]: https://example.com/ours\n=======\n\
+             [1.1.0]: https://example.com/theirs\n>>>>>>> theirs\n"
+        );
+        assert_eq!(merged.conflicts, 2);
+    }
+}
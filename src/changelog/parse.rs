@@ -0,0 +1,217 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{changelog::ChangeLogEntry, semver::SemVer};
+
+static VERSION_HEADING: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^#{1,2}\s+(?:Version\s+)?\[?v?(?P<version>\d+\.\d+\.\d+[^\]\s]*)\]?(?:\s+-\s+(?P<date>\S+))?\s*$",
+    )
+    .unwrap()
+});
+
+static UNRELEASED_HEADING: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^#{1,2}\s+\[?unreleased\]?").unwrap());
+
+static HEADING: Lazy<Regex> = Lazy::new(|| Regex::new(r"^#{1,3}\s+(?P<title>.+?)\s*$").unwrap());
+
+static ITEM: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[-*]\s+(?P<text>.*)$").unwrap());
+
+const INITIAL_COMMIT: &str = "Initial Commit";
+
+/// Read a changelog written by clog, or in the Keep a Changelog format.
+/// Anything outside of a released version, such as an `Unreleased`
+/// section, is skipped.
+pub fn parse_changelog(text: &str) -> Vec<ChangeLogEntry> {
+    let mut entries = vec![];
+    let mut in_release = false;
+
+    for line in text.lines() {
+        if let Some((version, date)) = parse_version_heading(line) {
+            entries.push(ChangeLogEntry::BumpVersion(version));
+            if let Some(date) = date {
+                entries.push(ChangeLogEntry::ReleaseDate(date));
+            }
+            in_release = true;
+        } else if UNRELEASED_HEADING.is_match(line) {
+            in_release = false;
+        } else if !in_release {
+            continue;
+        } else if let Some(caps) = HEADING.captures(line) {
+            entries.push(ChangeLogEntry::Category(caps["title"].to_string()));
+        } else if let Some(caps) = ITEM.captures(line) {
+            entries.push(ChangeLogEntry::Entry(caps["text"].trim_end().to_string()));
+        } else if line.starts_with(char::is_whitespace) && !line.trim().is_empty() {
+            // continuation of a multi-line or nested entry
+            if let Some(ChangeLogEntry::Entry(text)) = entries.last_mut() {
+                text.push('\n');
+                text.push_str(line.trim_end());
+            }
+        }
+    }
+
+    mark_initial_version(&mut entries);
+    entries
+}
+
+/// Whether a changelog already has a section for a version
+pub fn contains_version(text: &str, version: &SemVer) -> bool {
+    text.lines()
+        .filter_map(parse_version_heading)
+        .any(|(v, _)| v == *version)
+}
+
+/// Remove the section for a version, for example one left behind by a
+/// release commit which failed.
+pub fn remove_release(text: &str, version: &SemVer) -> String {
+    let mut out = String::new();
+    let mut removing = false;
+    for line in text.split_inclusive('\n') {
+        if let Some((v, _)) = parse_version_heading(line.trim_end()) {
+            removing = v == *version;
+        } else if UNRELEASED_HEADING.is_match(line) {
+            removing = false;
+        }
+        if !removing {
+            out.push_str(line);
+        }
+    }
+    out
+}
+
+/// The text of the newest `count` released versions
+pub fn newest_releases(text: &str, count: usize) -> String {
+    let mut out = String::new();
+    let mut seen = 0;
+    let mut in_release = false;
+    for line in text.split_inclusive('\n') {
+        if parse_version_heading(line.trim_end()).is_some() {
+            seen += 1;
+            in_release = seen <= count;
+        } else if UNRELEASED_HEADING.is_match(line) {
+            in_release = false;
+        }
+        if in_release {
+            out.push_str(line);
+        }
+    }
+    out
+}
+
+/// Whether `parse_changelog` keeps everything on a line of a release: a
+/// heading, an entry or its continuation, or a blank line. Anything else,
+/// like prose, is lost when the release is rendered again.
+pub fn is_structured(line: &str) -> bool {
+    let line = line.trim_end();
+    line.is_empty()
+        || (HEADING.is_match(line) && !UNRELEASED_HEADING.is_match(line))
+        || ITEM.is_match(line)
+        || line.starts_with(char::is_whitespace)
+}
+
+/// Index of the first line of the first released version
+pub fn first_release_line(text: &str) -> Option<usize> {
+    text.lines()
+        .position(|line| parse_version_heading(line).is_some())
+}
+
+pub fn parse_version_heading(line: &str) -> Option<(SemVer, Option<String>)> {
+    let caps = VERSION_HEADING.captures(line)?;
+    let version = SemVer::parse(&caps["version"]).ok()?;
+    Some((version, caps.name("date").map(|d| d.as_str().to_string())))
+}
+
+/// clog writes the first version of a project as a section with a single
+/// `Initial Commit` entry.
+fn mark_initial_version(entries: &mut Vec<ChangeLogEntry>) {
+    let start = match entries
+        .iter()
+        .rposition(|e| matches!(e, ChangeLogEntry::BumpVersion(_)))
+    {
+        Some(i) => i,
+        None => return,
+    };
+    let is_initial = matches!(
+        &entries[start + 1..],
+        [ChangeLogEntry::Entry(text)] if text == INITIAL_COMMIT
+    );
+    if is_initial {
+        if let ChangeLogEntry::BumpVersion(v) = entries[start].clone() {
+            entries.truncate(start);
+            entries.push(ChangeLogEntry::InitialVersion(v));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn v(version: &str) -> SemVer {
+        SemVer::parse(version).unwrap()
+    }
+
+    fn entry(text: &str) -> ChangeLogEntry {
+        ChangeLogEntry::Entry(text.to_string())
+    }
+
+    #[rstest]
+    #[case::clog(
+        "# Version 0.3.0\n- feat: test commit\n# Version 0.2.0\n- fix: bug in B\n- feat: add feature B\n# Version 0.1.0\n- Initial Commit\n",
+        vec![
+            ChangeLogEntry::BumpVersion(v("0.3.0")),
+            entry("feat: test commit"),
+            ChangeLogEntry::BumpVersion(v("0.2.0")),
+            entry("fix: bug in B"),
+            entry("feat: add feature B"),
+            ChangeLogEntry::InitialVersion(v("0.1.0")),
+        ]
+    )]
+    #[case::keep_a_changelog(
+        "# Changelog\n\nAll notable changes to this project will be documented in this file.\n\n\
+         ## [Unreleased]\n### Added\n- Not yet\n\n\
+         ## [1.1.0] - 2026-10-17\n### Added\n- A feature\n  over two lines\n### Fixed\n- A bug\n\n\
+         ## [1.0.0] - 2026-01-02\n- First\n\n\
+         [1.1.0]: https://example.com/compare/v1.0.0...v1.1.0\n",
+        vec![
+            ChangeLogEntry::BumpVersion(v("1.1.0")),
+            ChangeLogEntry::ReleaseDate("2026-10-17".to_string()),
+            ChangeLogEntry::Category("Added".to_string()),
+            entry("A feature\n  over two lines"),
+            ChangeLogEntry::Category("Fixed".to_string()),
+            entry("A bug"),
+            ChangeLogEntry::BumpVersion(v("1.0.0")),
+            ChangeLogEntry::ReleaseDate("2026-01-02".to_string()),
+            entry("First"),
+        ]
+    )]
+    #[case::empty("", vec![])]
+    fn test_parse_changelog(#[case] text: &str, #[case] expected: Vec<ChangeLogEntry>) {
+        assert_eq!(parse_changelog(text), expected);
+    }
+
+    #[test]
+    fn test_remove_release() {
+        let text = "# Version 0.3.0\n- feat: again\n# Version 0.3.0\n- feat: test\n# Version 0.2.0\n- fix: bug\n";
+        assert!(contains_version(text, &v("0.3.0")));
+        assert!(!contains_version(text, &v("0.4.0")));
+        assert_eq!(
+            remove_release(text, &v("0.3.0")),
+            "# Version 0.2.0\n- fix: bug\n"
+        );
+        assert_eq!(remove_release(text, &v("0.4.0")), text);
+    }
+
+    #[test]
+    fn test_newest_releases() {
+        let text = "# Changelog\n\n## [Unreleased]\n- Soon\n\n## [1.2.0] - 2026-10-01\n- C\n\n\
+                    ## [1.1.0] - 2026-09-01\n- B\n\n## [1.0.0] - 2026-08-01\n- A\n";
+        assert_eq!(
+            newest_releases(text, 2),
+            "## [1.2.0] - 2026-10-01\n- C\n\n## [1.1.0] - 2026-09-01\n- B\n\n"
+        );
+        assert_eq!(newest_releases("", 2), "");
+    }
+}
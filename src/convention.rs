@@ -0,0 +1,156 @@
+use regex::Regex;
+
+use crate::{IncludeRule, Patterns};
+
+/// A named set of bump patterns and changelog sections, chosen with
+/// `convention` in `clog.toml`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Convention {
+    /// Conventional Commits, listing only the commits which bump the version
+    #[default]
+    Conventional,
+    /// The Angular commit message guidelines
+    Angular,
+    /// gitmoji, as `:sparkles:` shortcodes or the emoji themselves
+    Gitmoji,
+    /// The rules of commitizen's `cz_conventional_commits`
+    Commitizen,
+}
+
+// bump patterns are matched against the lowercased commit message
+const SCOPE: &str = r"(\([^)]*\))?";
+pub(crate) const BREAKING_FOOTER: &str = r"(?m)^breaking[ -]change:";
+
+impl Convention {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "conventional" => Some(Self::Conventional),
+            "angular" => Some(Self::Angular),
+            "gitmoji" => Some(Self::Gitmoji),
+            "commitizen" => Some(Self::Commitizen),
+            _ => None,
+        }
+    }
+
+    pub fn patterns(self) -> Patterns {
+        match self {
+            Self::Conventional => Patterns::default(),
+            Self::Angular => Patterns {
+                major: regexes(&[r"^.*!:", BREAKING_FOOTER]),
+                minor: regexes(&[&format!("^feat{SCOPE}:")]),
+                patch: regexes(&[&format!("^(fix|perf){SCOPE}:")]),
+            },
+            Self::Gitmoji => Patterns {
+                major: regexes(&["^(:boom:|💥)"]),
+                minor: regexes(&["^(:sparkles:|✨)"]),
+                patch: regexes(&[
+                    "^(:bug:|🐛|:ambulance:|🚑|:lock:|🔒|:zap:|⚡|:adhesive_bandage:|🩹)",
+                ]),
+            },
+            Self::Commitizen => Patterns {
+                major: regexes(&[r"^.*!:", BREAKING_FOOTER]),
+                minor: regexes(&[&format!("^feat{SCOPE}:")]),
+                patch: regexes(&[&format!("^(fix|refactor|perf){SCOPE}:")]),
+            },
+        }
+    }
+
+    /// The sections commits are listed under, tried after any custom rules
+    pub fn include_rules(self) -> Vec<IncludeRule> {
+        let sections: &[(&str, &str)] = match self {
+            Self::Conventional => &[],
+            Self::Angular => &[
+                (r"^feat(\([^)]*\))?!?:", "Features"),
+                (r"^fix(\([^)]*\))?!?:", "Bug Fixes"),
+                (r"^perf(\([^)]*\))?!?:", "Performance Improvements"),
+                (r"^revert(\([^)]*\))?!?:", "Reverts"),
+            ],
+            Self::Gitmoji => &[
+                ("^(:sparkles:|✨)", "Added"),
+                ("^(:zap:|⚡)", "Performance"),
+                ("^(:lock:|🔒)", "Security"),
+                ("^(:bug:|🐛|:ambulance:|🚑|:adhesive_bandage:|🩹)", "Fixed"),
+                ("^(:memo:|📝)", "Documentation"),
+            ],
+            Self::Commitizen => &[
+                (r"^feat(\([^)]*\))?!?:", "Feat"),
+                (r"^fix(\([^)]*\))?!?:", "Fix"),
+                (r"^refactor(\([^)]*\))?!?:", "Refactor"),
+                (r"^perf(\([^)]*\))?!?:", "Perf"),
+            ],
+        };
+        sections
+            .iter()
+            .map(|(pattern, section)| IncludeRule {
+                // like the bump patterns, these ignore case
+                pattern: Regex::new(&format!("(?i){pattern}")).unwrap(),
+                section: Some(section.to_string()),
+            })
+            .collect()
+    }
+}
+
+fn regexes(patterns: &[&str]) -> Vec<Regex> {
+    patterns.iter().map(|p| Regex::new(p).unwrap()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{get_bump_from_heading, semver::SemVerBump, Config};
+
+    #[rstest]
+    #[case::angular_scoped_feat(
+        Convention::Angular,
+        "feat(parser): add a thing",
+        SemVerBump::Minor
+    )]
+    #[case::angular_perf(Convention::Angular, "perf: faster", SemVerBump::Patch)]
+    #[case::angular_footer(
+        Convention::Angular,
+        "refactor: new api\n\nBREAKING CHANGE: old api removed",
+        SemVerBump::Major
+    )]
+    #[case::angular_docs(Convention::Angular, "docs: typo", SemVerBump::None)]
+    #[case::gitmoji_shortcode(Convention::Gitmoji, ":sparkles: add a thing", SemVerBump::Minor)]
+    #[case::gitmoji_emoji(Convention::Gitmoji, "🐛 fix a crash", SemVerBump::Patch)]
+    #[case::gitmoji_boom(Convention::Gitmoji, "💥 drop the old api", SemVerBump::Major)]
+    #[case::gitmoji_memo(Convention::Gitmoji, ":memo: docs", SemVerBump::None)]
+    #[case::commitizen_refactor(Convention::Commitizen, "refactor: tidy", SemVerBump::Patch)]
+    #[case::conventional_scope(Convention::Conventional, "feat(x): thing", SemVerBump::None)]
+    fn test_preset_bumps(
+        #[case] convention: Convention,
+        #[case] message: &str,
+        #[case] expected: SemVerBump,
+    ) {
+        let config = Config {
+            patterns: convention.patterns(),
+            ..Default::default()
+        };
+        assert_eq!(get_bump_from_heading(&config, message), expected);
+    }
+
+    #[rstest]
+    #[case::angular(Convention::Angular, "feat(api): add a thing", Some("Features"))]
+    #[case::angular_capitalised(Convention::Angular, "Fix: a crash", Some("Bug Fixes"))]
+    #[case::commitizen_capitalised(Convention::Commitizen, "PERF!: faster", Some("Perf"))]
+    #[case::gitmoji_shortcode(Convention::Gitmoji, ":Memo: docs", Some("Documentation"))]
+    #[case::angular_docs(Convention::Angular, "Docs: typo", None)]
+    fn test_preset_sections(
+        #[case] convention: Convention,
+        #[case] first_line: &str,
+        #[case] expected: Option<&str>,
+    ) {
+        let rules = convention.include_rules();
+        let rule = rules.iter().find(|r| r.pattern.is_match(first_line));
+        assert_eq!(rule.and_then(|r| r.section.as_deref()), expected);
+    }
+
+    #[test]
+    fn test_unknown_convention() {
+        assert_eq!(Convention::from_name("gitmoji"), Some(Convention::Gitmoji));
+        assert_eq!(Convention::from_name("emoji"), None);
+    }
+}
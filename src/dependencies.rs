@@ -0,0 +1,369 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use toml::Table;
+
+use crate::{semver::SemVerBump, Person};
+
+/// The changelog section dependency updates are folded into
+pub const DEPENDENCIES_HEADING: &str = "Dependencies";
+
+/// The changelog section for the changes found in lockfiles
+pub const LOCKFILE_HEADING: &str = "Dependency updates";
+
+/// Lockfiles clog can read, at the root of the repo
+pub const LOCKFILES: &[&str] = &["Cargo.lock", "poetry.lock", "uv.lock", "package-lock.json"];
+
+static DEPS_SCOPE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\w+\(deps(-dev)?\):").unwrap());
+
+/// `chore(deps)!:` and the like, which break something and are never routine
+static BREAKING_HEADER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\w+(\([^)]*\))?!:").unwrap());
+
+/// Dependabot: `bump serde from 1.0.1 to 1.0.2`
+static DEPENDABOT_UPDATE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bbump (?P<package>\S+) from (?P<old>\S+) to (?P<new>\S+)").unwrap()
+});
+
+/// Renovate: `update rust crate serde to v1.0.2`, `update actions/checkout action to v4`
+static RENOVATE_UPDATE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\bupdate (?:rust crate |dependency |module |docker tag |npm package )?(?P<package>\S+)(?: action)? to v?(?P<new>\S+)",
+    )
+    .unwrap()
+});
+
+/// Recognises commits from dependency bots, set up with a `[dependencies]`
+/// table in `clog.toml`
+#[derive(Debug, Clone)]
+pub struct DependencyUpdates {
+    /// How much a dependency update bumps the version
+    pub bump: SemVerBump,
+    /// Authors whose commits are all dependency updates
+    bots: Vec<String>,
+}
+
+impl Default for DependencyUpdates {
+    fn default() -> Self {
+        Self {
+            bump: SemVerBump::Patch,
+            bots: vec!["dependabot[bot]".to_string(), "renovate[bot]".to_string()],
+        }
+    }
+}
+
+/// An update of one package, possibly folded from several commits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageUpdate {
+    pub package: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl PackageUpdate {
+    pub fn render(&self) -> String {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => format!("{} {} → {}", self.package, old, new),
+            (None, Some(new)) => format!("{} → {}", self.package, new),
+            _ => self.package.clone(),
+        }
+    }
+}
+
+impl DependencyUpdates {
+    pub fn from_toml(table: &Table) -> Self {
+        let mut updates = Self::default();
+        match table.get("bump").and_then(|v| v.as_str()) {
+            Some("none") => updates.bump = SemVerBump::None,
+            Some("patch") | None => {}
+            Some(other) => eprintln!(
+                "Ignoring dependency bump '{}', expected \"patch\" or \"none\"",
+                other
+            ),
+        }
+        updates.bots.extend(
+            table
+                .get("bots")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str())
+                .map(String::from),
+        );
+        updates
+    }
+
+    /// Whether a commit only updates dependencies, going by its author or a
+    /// `deps` scope such as `chore(deps):`. Breaking commits are not.
+    pub fn is_update(&self, message: &str, authors: &[Person]) -> bool {
+        let message = message.trim_start();
+        if BREAKING_HEADER.is_match(message) {
+            return false;
+        }
+        let by_bot = authors.first().is_some_and(|author| {
+            self.bots.iter().any(|bot| {
+                author.name.eq_ignore_ascii_case(bot) || author.email.contains(bot.as_str())
+            })
+        });
+        by_bot || DEPS_SCOPE.is_match(message)
+    }
+}
+
+/// The package and versions a dependency update commit names, falling back
+/// to its description
+pub fn parse_update(message: &str) -> PackageUpdate {
+    let subject = message.lines().next().unwrap_or("").trim();
+    if let Some(caps) = DEPENDABOT_UPDATE.captures(subject) {
+        return PackageUpdate {
+            package: caps["package"].to_string(),
+            old: Some(caps["old"].to_string()),
+            new: Some(caps["new"].to_string()),
+        };
+    }
+    if let Some(caps) = RENOVATE_UPDATE.captures(subject) {
+        return PackageUpdate {
+            package: caps["package"].to_string(),
+            old: None,
+            new: Some(caps["new"].to_string()),
+        };
+    }
+    let description = subject.split_once(": ").map_or(subject, |(_, d)| d);
+    PackageUpdate {
+        package: description.to_string(),
+        old: None,
+        new: None,
+    }
+}
+
+/// Fold updates of the same package together, given oldest first
+pub fn fold_updates(updates: impl Iterator<Item = PackageUpdate>) -> Vec<PackageUpdate> {
+    let mut folded: Vec<PackageUpdate> = vec![];
+    for update in updates {
+        match folded.iter_mut().find(|u| u.package == update.package) {
+            Some(existing) => {
+                existing.old = existing.old.take().or(update.old);
+                existing.new = update.new.or(existing.new.take());
+            }
+            None => folded.push(update),
+        }
+    }
+    folded
+}
+
+/// The versions of each package in a lockfile. Lockfiles may hold several
+/// versions of one package.
+fn locked_packages(file_name: &str, content: &str) -> BTreeMap<String, BTreeSet<String>> {
+    let mut packages: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut add = |name: &str, version: &str| {
+        packages
+            .entry(name.to_string())
+            .or_default()
+            .insert(version.to_string());
+    };
+
+    if file_name == "package-lock.json" {
+        let Ok(lock) = serde_json::from_str::<serde_json::Value>(content) else {
+            eprintln!("Ignoring {} which is not valid JSON", file_name);
+            return packages;
+        };
+        // lockfile v2 and v3 list packages by path, v1 by name
+        if let Some(entries) = lock.get("packages").and_then(|p| p.as_object()) {
+            for (path, package) in entries {
+                let Some((_, name)) = path.rsplit_once("node_modules/") else {
+                    continue;
+                };
+                if let Some(version) = package.get("version").and_then(|v| v.as_str()) {
+                    add(name, version);
+                }
+            }
+        } else if let Some(entries) = lock.get("dependencies").and_then(|p| p.as_object()) {
+            for (name, package) in entries {
+                if let Some(version) = package.get("version").and_then(|v| v.as_str()) {
+                    add(name, version);
+                }
+            }
+        }
+    } else {
+        // Cargo.lock, poetry.lock and uv.lock share a `[[package]]` layout
+        let Ok(lock) = content.parse::<Table>() else {
+            eprintln!("Ignoring {} which is not valid TOML", file_name);
+            return packages;
+        };
+        for package in lock
+            .get("package")
+            .and_then(|p| p.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|p| p.as_table())
+        {
+            if let (Some(name), Some(version)) = (
+                package.get("name").and_then(|v| v.as_str()),
+                package.get("version").and_then(|v| v.as_str()),
+            ) {
+                add(name, version);
+            }
+        }
+    }
+    packages
+}
+
+/// The packages added, removed and upgraded between two versions of a
+/// lockfile, which may not exist at either end
+pub fn lockfile_changes(file_name: &str, old: Option<&str>, new: Option<&str>) -> Vec<String> {
+    let old = old.map_or_else(BTreeMap::new, |c| locked_packages(file_name, c));
+    let new = new.map_or_else(BTreeMap::new, |c| locked_packages(file_name, c));
+    let empty = BTreeSet::new();
+
+    // packages can be locked at more than one version, so the versions are
+    // compared one by one
+    let mut changes = vec![];
+    let names = new
+        .keys()
+        .chain(old.keys().filter(|name| !new.contains_key(*name)));
+    for name in names {
+        let old_versions = old.get(name).unwrap_or(&empty);
+        let new_versions = new.get(name).unwrap_or(&empty);
+        let removed = old_versions.difference(new_versions).collect_vec();
+        let added = new_versions.difference(old_versions).collect_vec();
+        match (removed.as_slice(), added.as_slice()) {
+            ([from], [to]) => changes.push(format!("{} {} → {}", name, from, to)),
+            _ => {
+                changes.extend(added.iter().map(|v| format!("{} {} (added)", name, v)));
+                changes.extend(removed.iter().map(|v| format!("{} {} (removed)", name, v)));
+            }
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn person(name: &str, email: &str) -> Person {
+        Person {
+            name: name.to_string(),
+            email: email.to_string(),
+        }
+    }
+
+    #[rstest]
+    #[case::dependabot(
+        "chore(deps): bump serde from 1.0.1 to 1.0.2 in /crates",
+        "serde 1.0.1 → 1.0.2"
+    )]
+    #[case::dependabot_plain("Bump regex from 1.11.0 to 1.12.2 (#40)", "regex 1.11.0 → 1.12.2")]
+    #[case::renovate_crate("fix(deps): update rust crate toml to v0.9.8", "toml → 0.9.8")]
+    #[case::renovate_action(
+        "chore(deps): update actions/checkout action to v5",
+        "actions/checkout → 5"
+    )]
+    #[case::other("build(deps): refresh the lockfile", "refresh the lockfile")]
+    fn test_parse_update(#[case] message: &str, #[case] expected: &str) {
+        assert_eq!(parse_update(message).render(), expected);
+    }
+
+    #[rstest]
+    #[case::deps_scope("chore(deps): bump a from 1 to 2", "Test User", true)]
+    #[case::dev_deps_scope("build(deps-dev): bump a from 1 to 2", "Test User", true)]
+    #[case::bot("Update dependency a to v2", "renovate[bot]", true)]
+    #[case::other_scope("chore(ci): bump a from 1 to 2", "Test User", false)]
+    #[case::breaking_deps_scope("chore(deps)!: drop serde 0.x", "Test User", false)]
+    #[case::breaking_bot("build(deps)!: bump a from 1 to 2", "dependabot[bot]", false)]
+    fn test_is_update(#[case] message: &str, #[case] author: &str, #[case] expected: bool) {
+        let updates = DependencyUpdates::default();
+        let authors = [person(author, "someone@example.com")];
+        assert_eq!(updates.is_update(message, &authors), expected);
+    }
+
+    #[test]
+    fn test_fold_updates() {
+        let folded = fold_updates(
+            [
+                "bump serde from 1.0.1 to 1.0.2",
+                "bump regex from 1.0.0 to 1.1.0",
+                "bump serde from 1.0.2 to 1.0.3",
+            ]
+            .into_iter()
+            .map(parse_update),
+        );
+        let rendered: Vec<_> = folded.iter().map(|u| u.render()).collect();
+        assert_eq!(rendered, vec!["serde 1.0.1 → 1.0.3", "regex 1.0.0 → 1.1.0"]);
+    }
+
+    #[test]
+    fn test_cargo_lockfile_changes() {
+        let old = r#"
+            version = 4
+
+            [[package]]
+            name = "clog"
+            version = "0.3.0"
+
+            [[package]]
+            name = "serde"
+            version = "1.0.1"
+
+            [[package]]
+            name = "syn"
+            version = "1.0.109"
+
+            [[package]]
+            name = "syn"
+            version = "2.0.1"
+        "#;
+        let new = r#"
+            [[package]]
+            name = "clog"
+            version = "0.3.0"
+
+            [[package]]
+            name = "regex"
+            version = "1.12.2"
+
+            [[package]]
+            name = "serde"
+            version = "1.0.2"
+
+            [[package]]
+            name = "syn"
+            version = "2.0.1"
+        "#;
+        assert_eq!(
+            lockfile_changes("Cargo.lock", Some(old), Some(new)),
+            vec![
+                "regex 1.12.2 (added)",
+                "serde 1.0.1 → 1.0.2",
+                "syn 1.0.109 (removed)",
+            ]
+        );
+        assert_eq!(
+            lockfile_changes("Cargo.lock", Some(new), Some(old)),
+            vec![
+                "serde 1.0.2 → 1.0.1",
+                "syn 1.0.109 (added)",
+                "regex 1.12.2 (removed)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_package_lock_changes() {
+        let old = r#"{"packages": {"": {"version": "1.0.0"},
+            "node_modules/left-pad": {"version": "1.0.0"},
+            "node_modules/a/node_modules/b": {"version": "2.0.0"}}}"#;
+        let new = r#"{"packages": {"": {"version": "1.0.0"},
+            "node_modules/left-pad": {"version": "1.3.0"}}}"#;
+        assert_eq!(
+            lockfile_changes("package-lock.json", Some(old), Some(new)),
+            vec!["left-pad 1.0.0 → 1.3.0", "b 2.0.0 (removed)"]
+        );
+        assert_eq!(
+            lockfile_changes("package-lock.json", None, Some(new)),
+            vec!["left-pad 1.3.0 (added)"]
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use git2::Repository;
+
+use crate::{git::edit_text, semver::SemVerBump, Config};
+
+/// Where changelog fragments wait for the next release
+pub const FRAGMENTS_DIR: &str = ".changes";
+
+/// A hand-written changelog entry, added in the change it describes and
+/// released with the next version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fragment {
+    pub path: PathBuf,
+    pub bump: SemVerBump,
+    /// The section the entry is listed under
+    pub category: Option<String>,
+    pub text: String,
+}
+
+pub fn parse_bump(bump: &str) -> Option<SemVerBump> {
+    match bump.to_lowercase().as_str() {
+        "none" => Some(SemVerBump::None),
+        "patch" => Some(SemVerBump::Patch),
+        "minor" => Some(SemVerBump::Minor),
+        "major" => Some(SemVerBump::Major),
+        _ => None,
+    }
+}
+
+impl Fragment {
+    /// Read a fragment: optional `---` front matter with `bump` and
+    /// `category`, then the Markdown text of the entry
+    pub fn parse(path: &Path, content: &str) -> anyhow::Result<Self> {
+        let mut fragment = Self {
+            path: path.to_path_buf(),
+            bump: SemVerBump::None,
+            category: None,
+            text: String::new(),
+        };
+
+        let mut body = content;
+        if let Some(rest) = content.strip_prefix("---\n") {
+            let (front_matter, rest) = rest
+                .split_once("\n---\n")
+                .or_else(|| rest.strip_suffix("\n---").map(|f| (f, "")))
+                .ok_or_else(|| anyhow!("unterminated front matter"))?;
+            for line in front_matter.lines().filter(|l| !l.trim().is_empty()) {
+                let (key, value) = line
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("expected `key: value`, found '{}'", line))?;
+                let value = value.trim();
+                match key.trim() {
+                    "bump" => {
+                        fragment.bump = parse_bump(value)
+                            .ok_or_else(|| anyhow!("unknown bump level '{}'", value))?
+                    }
+                    "category" if !value.is_empty() => fragment.category = Some(value.to_string()),
+                    "category" => {}
+                    other => return Err(anyhow!("unknown front matter key '{}'", other)),
+                }
+            }
+            body = rest;
+        }
+
+        // continuation lines are indented to stay in the list item
+        fragment.text = body.trim().lines().collect::<Vec<_>>().join("\n  ");
+        if fragment.text.is_empty() {
+            return Err(anyhow!("the fragment has no text"));
+        }
+        Ok(fragment)
+    }
+}
+
+/// The fragments waiting for the next release of the project in `dir`,
+/// oldest first. Fragments which cannot be read are skipped with a warning.
+pub fn pending(dir: &Path) -> Vec<Fragment> {
+    let Ok(entries) = fs::read_dir(dir.join(FRAGMENTS_DIR)) else {
+        return vec![];
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "md"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let parsed = fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Fragment::parse(&path, &content));
+            match parsed {
+                Ok(fragment) => Some(fragment),
+                Err(e) => {
+                    eprintln!("Ignoring changelog fragment {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Delete fragments once they are in the changelog
+pub fn remove(fragments: &[Fragment]) -> anyhow::Result<()> {
+    for fragment in fragments {
+        fs::remove_file(&fragment.path)?;
+    }
+    Ok(())
+}
+
+/// Write a new fragment, named after when it was made and its first words.
+/// Without any text, the fragment is written in an editor.
+pub fn add(
+    repo: Option<&Repository>,
+    config: &Config,
+    bump: SemVerBump,
+    category: Option<&str>,
+    text: Option<&str>,
+) -> anyhow::Result<PathBuf> {
+    let content = match text {
+        Some(text) => render(bump, category, text),
+        None => edit_text(repo, &render(bump, category, ""))?,
+    };
+    let fragments_dir = config.path.join(FRAGMENTS_DIR);
+    let fragment = Fragment::parse(&fragments_dir, &content)?;
+    let slug: String = fragment
+        .text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .take(5)
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    let stamp = chrono::Local::now().format("%Y%m%d%H%M%S");
+
+    fs::create_dir_all(&fragments_dir)?;
+    let path = fragments_dir.join(format!("{}-{}.md", stamp, slug));
+    fs::write(&path, &content)?;
+    Ok(path)
+}
+
+/// Contents of a fragment file, also the template when writing one in an
+/// editor
+pub fn render(bump: SemVerBump, category: Option<&str>, text: &str) -> String {
+    format!(
+        "---\nbump: {}\ncategory: {}\n---\n{}\n",
+        bump,
+        category.unwrap_or(""),
+        text.trim()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_fragment() {
+        let fragment = Fragment::parse(
+            Path::new("a.md"),
+            "---\nbump: minor\ncategory: Added\n---\nA new `--flag`.\nIt does things.\n",
+        )
+        .unwrap();
+        assert_eq!(fragment.bump, SemVerBump::Minor);
+        assert_eq!(fragment.category.as_deref(), Some("Added"));
+        assert_eq!(fragment.text, "A new `--flag`.\n  It does things.");
+    }
+
+    #[test]
+    fn test_parse_without_front_matter() {
+        let fragment = Fragment::parse(Path::new("a.md"), "Just some text\n").unwrap();
+        assert_eq!(fragment.bump, SemVerBump::None);
+        assert_eq!(fragment.category, None);
+        assert_eq!(fragment.text, "Just some text");
+    }
+
+    #[rstest]
+    #[case::bad_bump("---\nbump: huge\n---\ntext")]
+    #[case::unknown_key("---\nkind: x\n---\ntext")]
+    #[case::unterminated("---\nbump: minor\ntext")]
+    #[case::empty("---\nbump: minor\ncategory: Added\n---\n")]
+    fn test_parse_invalid(#[case] content: &str) {
+        assert!(Fragment::parse(Path::new("a.md"), content).is_err());
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        let content = render(SemVerBump::Patch, Some("Fixed"), "A crash on start\n");
+        let fragment = Fragment::parse(Path::new("a.md"), &content).unwrap();
+        assert_eq!(fragment.bump, SemVerBump::Patch);
+        assert_eq!(fragment.category.as_deref(), Some("Fixed"));
+        assert_eq!(fragment.text, "A crash on start");
+    }
+}
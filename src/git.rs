@@ -1,19 +1,34 @@
+use std::{io::Write, process::Command};
+
 use anyhow::anyhow;
 use git2::{
-    Commit, DiffFormat, DiffOptions, Oid, Repository, Revwalk, Signature, Sort, StatusOptions,
+    build::CheckoutBuilder, Commit, Diff, DiffFormat, DiffOptions, DiffStatsFormat, Mailmap,
+    ObjectType, Oid, Patch, Pathspec, PathspecFlags, Repository, Revwalk, Signature, Sort, Status,
+    StatusOptions,
 };
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+mod sign;
+
+use sign::Signer;
 
 use crate::{
-    is_last_version_bump_clog, iterate_to_last_version, semver::SemVer, Config, HistoryItem,
-    HistoryItemKind, Project,
+    fragment::FRAGMENTS_DIR, is_last_version_bump_clog, iterate_to_last_version, release::TagType,
+    semver::SemVer, Config, HistoryItem, HistoryItemKind, Person, Project,
 };
 
 static CLOG_TRAILER: &str = "Bumped-by: clog";
 
+static CO_AUTHOR_TRAILER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?mi)^co-authored-by:\s*(?P<name>.+?)\s*<(?P<email>[^>]+)>\s*$").unwrap()
+});
+
 pub struct GitHistory<'repo> {
     project: &'repo dyn Project,
     repo: &'repo Repository,
     revwalk: Revwalk<'repo>,
+    mailmap: Option<Mailmap>,
 }
 
 impl<'repo> GitHistory<'repo> {
@@ -25,8 +40,30 @@ impl<'repo> GitHistory<'repo> {
             project,
             repo,
             revwalk,
+            mailmap: repo.mailmap().ok(),
         }
     }
+
+    /// The commits reachable from `to` but not from `from`
+    pub fn between(
+        project: &'repo dyn Project,
+        repo: &'repo Repository,
+        from: Option<Oid>,
+        to: Oid,
+    ) -> anyhow::Result<Self> {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL)?;
+        revwalk.push(to)?;
+        if let Some(from) = from {
+            revwalk.hide(from)?;
+        }
+        Ok(Self {
+            project,
+            repo,
+            revwalk,
+            mailmap: repo.mailmap().ok(),
+        })
+    }
 }
 
 impl<'repo> Iterator for GitHistory<'repo> {
@@ -37,7 +74,9 @@ impl<'repo> Iterator for GitHistory<'repo> {
             .by_ref()
             .filter_map(|oid| oid.ok())
             .filter_map(|oid| self.repo.find_commit(oid).ok())
-            .filter_map(|commit| CommitWrapper::parse_commit(self.project, self.repo, commit).ok())
+            .filter_map(|commit| {
+                CommitWrapper::parse(self.project, self.repo, commit, self.mailmap.as_ref()).ok()
+            })
             .next()
     }
 }
@@ -47,7 +86,9 @@ pub struct CommitWrapper {
     message: String,
     version: crate::semver::SemVer,
     id: Oid,
+    time: i64,
     kind: HistoryItemKind,
+    authors: Vec<Person>,
 }
 
 impl CommitWrapper {
@@ -55,6 +96,15 @@ impl CommitWrapper {
         project: &dyn Project,
         repo: &Repository,
         commit: Commit,
+    ) -> anyhow::Result<Self> {
+        Self::parse(project, repo, commit, repo.mailmap().ok().as_ref())
+    }
+
+    fn parse(
+        project: &dyn Project,
+        repo: &Repository,
+        commit: Commit,
+        mailmap: Option<&Mailmap>,
     ) -> anyhow::Result<Self> {
         let message = commit
             .message()
@@ -71,15 +121,43 @@ impl CommitWrapper {
         let text = std::str::from_utf8(blob.content())?.to_string();
         let version = project.parse_version_file(&text)?;
         let id = commit.id();
+        let time = commit.time().seconds();
         let kind = Self::parse_commit_kind(&commit);
+        let authors = Self::parse_authors(&commit, mailmap);
         Ok(Self {
             message: message.to_string(),
             version,
             id,
+            time,
             kind,
+            authors,
         })
     }
 
+    /// The author and any `Co-authored-by:` trailers, with identities
+    /// normalised through `.mailmap`
+    fn parse_authors(commit: &Commit, mailmap: Option<&Mailmap>) -> Vec<Person> {
+        let resolve = |sig: Signature| {
+            let sig = match mailmap {
+                Some(m) => m.resolve_signature(&sig).unwrap_or(sig),
+                None => sig,
+            };
+            Person {
+                name: sig.name().unwrap_or("").to_string(),
+                email: sig.email().unwrap_or("").to_string(),
+            }
+        };
+
+        let mut authors = vec![resolve(commit.author())];
+        let message = commit.message().unwrap_or("");
+        for caps in CO_AUTHOR_TRAILER.captures_iter(message) {
+            if let Ok(sig) = Signature::now(&caps["name"], &caps["email"]) {
+                authors.push(resolve(sig));
+            }
+        }
+        authors
+    }
+
     fn parse_commit_kind(commit: &Commit) -> HistoryItemKind {
         let message = commit.message().unwrap_or("");
         match message.contains(CLOG_TRAILER) {
@@ -105,6 +183,14 @@ impl HistoryItem for CommitWrapper {
     fn commit_id(&self) -> Option<Oid> {
         Some(self.id)
     }
+
+    fn time(&self) -> Option<i64> {
+        Some(self.time)
+    }
+
+    fn authors(&self) -> Vec<Person> {
+        self.authors.clone()
+    }
 }
 
 /// Create a bump commit on the current branch
@@ -114,14 +200,11 @@ pub fn create_clog_commit(
     config: &Config,
     next_version: SemVer,
 ) -> anyhow::Result<()> {
-    let message = make_clog_commit_message(&project.get_version(), &next_version);
+    let previous_version = project.get_version();
+    let message = make_clog_commit_message(config, &previous_version, &next_version);
     project.set_version(next_version.clone());
     project.update_project_file()?;
-    // get your user?
-    let sig = match repo.signature() {
-        Ok(s) => s,
-        Err(_) => Signature::now(&config.name, &config.email)?,
-    };
+    let sig = release_signature(repo, config)?;
     let tree_id = {
         let mut index = repo.index().unwrap();
         let version_file = project.get_version_file();
@@ -133,6 +216,14 @@ pub fn create_clog_commit(
         for file in project.get_extra_files(config)? {
             index.add_path(&file)?
         }
+        if let Some(spec) = &config.rpm_spec {
+            index.add_path(spec)?;
+        }
+        if let Some(json) = &config.release_notes_json {
+            index.add_path(json)?;
+        }
+        // the fragments released in this version have been deleted
+        index.update_all([FRAGMENTS_DIR], None)?;
         index.write()?;
         index.write_tree()?
     };
@@ -145,18 +236,167 @@ pub fn create_clog_commit(
         .and_then(|h| h.target())
         .and_then(|oid| repo.find_commit(oid).ok());
 
-    if let Some(parent) = parent_commit {
-        repo.commit(Some("HEAD"), &sig, &sig, &message, &tree, &[&parent])?;
-    } else {
-        repo.commit(Some("HEAD"), &sig, &sig, &message, &tree, &[])?;
+    let parents: Vec<&Commit> = parent_commit.iter().collect();
+    let signer = Signer::for_commits(repo)?;
+    let oid = write_commit(repo, signer.as_ref(), &sig, &sig, &message, &tree, &parents)?;
+    // the tag is made before HEAD moves, so a release which cannot be tagged
+    // leaves no release commit behind
+    create_release_tag(repo, config, &sig, oid, &previous_version, &next_version)?;
+    update_head(repo, oid, &message)?;
+
+    Ok(())
+}
+
+/// Tag the release commit as `[release]` in `clog.toml` says
+fn create_release_tag(
+    repo: &Repository,
+    config: &Config,
+    sig: &Signature,
+    oid: Oid,
+    previous_version: &SemVer,
+    next_version: &SemVer,
+) -> anyhow::Result<()> {
+    let release = &config.release;
+    if release.tag_type == TagType::None {
+        return Ok(());
+    }
+    let obj = repo.find_object(oid, Some(ObjectType::Commit))?;
+    let tag = release.tag_name(previous_version, next_version);
+    if !git2::Reference::is_valid_name(&format!("refs/tags/{}", tag)) {
+        return Err(anyhow!(
+            "'{}' is not a valid tag name, check tag_name in clog.toml",
+            tag
+        ));
+    }
+    let tag_message = release.tag_message(previous_version, next_version);
+    match (release.tag_type, Signer::for_tags(repo)?) {
+        (TagType::Lightweight, _) => {
+            repo.tag_lightweight(&tag, &obj, true)?;
+        }
+        (_, Some(signer)) => create_signed_tag(repo, &tag, &obj, sig, &tag_message, &signer)?,
+        (_, None) => {
+            repo.tag(&tag, &obj, sig, &tag_message, true)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a commit, signed by `signer` if there is one, without moving any
+/// reference to it
+fn write_commit(
+    repo: &Repository,
+    signer: Option<&Signer>,
+    author: &Signature,
+    committer: &Signature,
+    message: &str,
+    tree: &git2::Tree,
+    parents: &[&Commit],
+) -> anyhow::Result<Oid> {
+    let Some(signer) = signer else {
+        return Ok(repo.commit(None, author, committer, message, tree, parents)?);
     };
-    let obj = repo.revparse_single("HEAD")?;
-    let tag = format!("v{next_version}");
-    repo.tag(&tag, &obj, &sig, &format!("Release {next_version}"), true)?;
+    let buffer = repo.commit_create_buffer(author, committer, message, tree, parents)?;
+    let buffer = buffer
+        .as_str()
+        .ok_or_else(|| anyhow!("The commit to sign is not UTF-8"))?;
+    let signature = signer.sign(buffer, committer)?;
+    Ok(repo.commit_signed(buffer, &signature, None)?)
+}
+
+/// Point `HEAD`, or the branch it is on, at a new commit, as `repo.commit`
+/// does for the commits it makes
+fn update_head(repo: &Repository, oid: Oid, message: &str) -> anyhow::Result<()> {
+    let summary = message.lines().next().unwrap_or_default();
+    let log_message = format!("commit: {}", summary);
+    let head = repo.find_reference("HEAD")?;
+    match head.symbolic_target() {
+        Some(branch) => {
+            repo.reference(branch, oid, true, &log_message)?;
+        }
+        None => repo.set_head_detached(oid)?,
+    }
+    Ok(())
+}
 
+/// Write an annotated tag object with a signature after the message, which
+/// `repo.tag` cannot make
+fn create_signed_tag(
+    repo: &Repository,
+    name: &str,
+    target: &git2::Object,
+    tagger: &Signature,
+    message: &str,
+    signer: &Signer,
+) -> anyhow::Result<()> {
+    let when = tagger.when();
+    let offset = when.offset_minutes();
+    let mut tag = format!(
+        "object {}\ntype {}\ntag {}\ntagger {} <{}> {} {}{:02}{:02}\n\n{}\n",
+        target.id(),
+        target.kind().map(|k| k.str()).unwrap_or("commit"),
+        name,
+        tagger.name().unwrap_or_default(),
+        tagger.email().unwrap_or_default(),
+        when.seconds(),
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60,
+        message.trim_end(),
+    );
+    let signature = signer.sign(&tag, tagger)?;
+    tag.push_str(&signature);
+    let oid = repo.odb()?.write(ObjectType::Tag, tag.as_bytes())?;
+    repo.reference(&format!("refs/tags/{}", name), oid, true, "")?;
     Ok(())
 }
 
+/// The identity used for release commits, falling back to clog's own when
+/// the user has not configured git.
+pub(crate) fn release_signature<'a>(
+    repo: &Repository,
+    config: &'a Config,
+) -> anyhow::Result<Signature<'a>> {
+    match repo.signature() {
+        Ok(s) => Ok(s),
+        Err(_) => Ok(Signature::now(&config.name, &config.email)?),
+    }
+}
+
+/// Open text in the user's editor, chosen the same way git chooses one, and
+/// return what they saved.
+pub(crate) fn edit_text(repo: Option<&Repository>, text: &str) -> anyhow::Result<String> {
+    let editor = std::env::var("GIT_EDITOR")
+        .ok()
+        .or_else(|| {
+            repo.and_then(|r| r.config().ok())
+                .and_then(|c| c.get_string("core.editor").ok())
+        })
+        .or_else(|| std::env::var("VISUAL").ok())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .unwrap_or_else(|| "vi".to_string());
+
+    let mut file = tempfile::Builder::new()
+        .prefix("CLOG_EDITMSG")
+        .suffix(".md")
+        .tempfile()?;
+    file.write_all(text.as_bytes())?;
+    file.flush()?;
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$@\""))
+        .arg(&editor)
+        .arg(file.path())
+        .status()
+        .map_err(|e| anyhow!("Failed to launch editor '{}': {}", editor, e))?;
+    if !status.success() {
+        return Err(anyhow!("Editor '{}' exited with status {}", editor, status));
+    }
+
+    Ok(std::fs::read_to_string(file.path())?)
+}
+
 pub fn remove_last_release_commit(repo: &Repository, project: &dyn Project) -> anyhow::Result<()> {
     let history = GitHistory::new(project, repo);
     if !is_last_version_bump_clog(history) {
@@ -180,29 +420,29 @@ pub fn remove_last_release_commit(repo: &Repository, project: &dyn Project) -> a
     repo.set_head_detached(base.id())?;
     repo.checkout_head(None)?;
 
-    let mut iter = commits.iter();
-    iter.next();
-    for c in iter {
+    // the commits after the release are made again on top of its parent,
+    // signed as git would sign new commits
+    let signer = Signer::for_commits(repo)?;
+    let mut new_head = base;
+    for c in commits.iter().skip(1) {
         let commit = repo.find_commit(c.id)?;
-        let tree = commit.tree()?;
-
-        let parent = repo.head()?.peel_to_commit()?;
-        let parents = [&parent];
-
-        repo.commit(
-            Some("HEAD"),
+        let oid = write_commit(
+            repo,
+            signer.as_ref(),
             &commit.author(),
             &commit.committer(),
             commit.message().unwrap_or(""),
-            &tree,
-            &parents,
+            &commit.tree()?,
+            &[&new_head],
         )?;
+        new_head = repo.find_commit(oid)?;
     }
 
-    let new_head = repo.head()?.target().unwrap();
     let mut reference = repo.find_reference(&branch_ref)?;
-    reference.set_target(new_head, "drop release commit")?;
+    reference.set_target(new_head.id(), "drop release commit")?;
     repo.set_head(&branch_ref)?;
+    // bring back the fragments the release used up
+    repo.checkout_head(Some(CheckoutBuilder::new().force().path(FRAGMENTS_DIR)))?;
 
     Ok(())
 }
@@ -225,7 +465,14 @@ pub fn generate_diff_for_window(
     }
 }
 
-pub(crate) fn diff_oids(repo: &Repository, newest: Oid, oldest: Oid) -> anyhow::Result<String> {
+/// The changes made by the commits from `oldest` to `newest`, to only the
+/// given `paths` if there are any
+fn tree_diff<'repo>(
+    repo: &'repo Repository,
+    newest: Oid,
+    oldest: Oid,
+    paths: &[String],
+) -> anyhow::Result<Diff<'repo>> {
     let oldest_commit = repo.find_commit(oldest)?;
     let base_tree = match oldest_commit.parent(0) {
         std::result::Result::Ok(parent) => parent.tree()?,
@@ -242,9 +489,17 @@ pub(crate) fn diff_oids(repo: &Repository, newest: Oid, oldest: Oid) -> anyhow::
         .include_ignored(false)
         .include_untracked(false)
         .patience(true)
-        .minimal(true);
+        .minimal(true)
+        .disable_pathspec_match(true);
+    for path in paths {
+        opts.pathspec(path);
+    }
+
+    Ok(repo.diff_tree_to_tree(Some(&base_tree), Some(&head_tree), Some(&mut opts))?)
+}
 
-    let diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&head_tree), Some(&mut opts))?;
+pub(crate) fn diff_oids(repo: &Repository, newest: Oid, oldest: Oid) -> anyhow::Result<String> {
+    let diff = tree_diff(repo, newest, oldest, &[])?;
 
     let mut diff_str = String::new();
     diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
@@ -255,6 +510,142 @@ pub(crate) fn diff_oids(repo: &Repository, newest: Oid, oldest: Oid) -> anyhow::
     Ok(diff_str)
 }
 
+/// The patch for one file in a diff
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FileDiff {
+    pub path: String,
+    pub patch: String,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// The changes from `oldest` to `newest` file by file, leaving out files
+/// matching the `exclude` pathspecs
+pub(crate) fn file_diffs(
+    repo: &Repository,
+    newest: Oid,
+    oldest: Oid,
+    exclude: &[String],
+) -> anyhow::Result<Vec<FileDiff>> {
+    let diff = tree_diff(repo, newest, oldest, &[])?;
+    let exclude = Pathspec::new(exclude)?;
+
+    let mut files = vec![];
+    for (i, delta) in diff.deltas().enumerate() {
+        let Some(path) = delta.new_file().path().or(delta.old_file().path()) else {
+            continue;
+        };
+        if exclude.matches_path(path, PathspecFlags::DEFAULT) {
+            continue;
+        }
+        let Some(mut patch) = Patch::from_diff(&diff, i)? else {
+            continue;
+        };
+        let (_, insertions, deletions) = patch.line_stats()?;
+        files.push(FileDiff {
+            path: path.to_string_lossy().into_owned(),
+            patch: patch.to_buf()?.as_str().unwrap_or("").to_string(),
+            insertions,
+            deletions,
+        });
+    }
+    Ok(files)
+}
+
+/// `git diff --stat` over the commits from `oldest` to `newest`, leaving out
+/// files matching the `exclude` pathspecs
+pub(crate) fn diff_stat_oids(
+    repo: &Repository,
+    newest: Oid,
+    oldest: Oid,
+    exclude: &[String],
+) -> anyhow::Result<String> {
+    let exclude = Pathspec::new(exclude)?;
+    let paths: Vec<String> = tree_diff(repo, newest, oldest, &[])?
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+        .filter(|path| !exclude.matches_path(path, PathspecFlags::DEFAULT))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    if paths.is_empty() {
+        return Ok(String::new());
+    }
+    let diff = tree_diff(repo, newest, oldest, &paths)?;
+    let stats = diff.stats()?.to_buf(DiffStatsFormat::FULL, 80)?;
+    Ok(stats.as_str().unwrap_or("").to_string())
+}
+
+/// The contents of a file at the root of the repo as of `base`, or the empty
+/// repo, and as of `newest`
+pub(crate) fn file_before_and_after(
+    repo: &Repository,
+    newest: Oid,
+    base: Option<Oid>,
+    path: &str,
+) -> anyhow::Result<(Option<String>, Option<String>)> {
+    let read = |tree: Option<git2::Tree>| -> anyhow::Result<Option<String>> {
+        let Some(entry) = tree.and_then(|t| t.get_path(std::path::Path::new(path)).ok()) else {
+            return Ok(None);
+        };
+        let blob = entry.to_object(repo)?.peel_to_blob()?;
+        Ok(Some(String::from_utf8_lossy(blob.content()).into_owned()))
+    };
+    let before = match base {
+        Some(base) => Some(repo.find_commit(base)?.tree()?),
+        None => None,
+    };
+    let after = Some(repo.find_commit(newest)?.tree()?);
+    Ok((read(before)?, read(after)?))
+}
+
+/// Where `HEAD` points and the contents of the tracked files which differ
+/// from it, to tell whether anything has changed them
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct TrackedState {
+    head: Option<Oid>,
+    files: Vec<(String, Status, Option<Oid>)>,
+}
+
+impl TrackedState {
+    pub fn new(repo: &Repository) -> anyhow::Result<Self> {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(false).include_ignored(false);
+        let workdir = repo.workdir();
+        let files = repo
+            .statuses(Some(&mut opts))?
+            .iter()
+            .map(|entry| {
+                let path = entry.path().unwrap_or_default().to_string();
+                let contents = workdir
+                    .map(|w| w.join(&path))
+                    .filter(|p| p.is_file())
+                    .map(|p| Oid::hash_file(ObjectType::Blob, p))
+                    .transpose()?;
+                Ok((path, entry.status(), contents))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            head: repo.head().ok().and_then(|h| h.target()),
+            files,
+        })
+    }
+
+    /// What is different in `other`
+    pub fn changes(&self, other: &Self) -> Vec<String> {
+        let mut changes = vec![];
+        if self.head != other.head {
+            changes.push("HEAD".to_string());
+        }
+        for file in self.files.iter().chain(&other.files) {
+            let unchanged = self.files.contains(file) && other.files.contains(file);
+            if !unchanged && !changes.contains(&file.0) {
+                changes.push(file.0.clone());
+            }
+        }
+        changes
+    }
+}
+
 fn repo_is_clean(repo: &Repository) -> bool {
     let mut opts = StatusOptions::new();
     opts.include_untracked(true)
@@ -263,8 +654,11 @@ fn repo_is_clean(repo: &Repository) -> bool {
     repo.statuses(Some(&mut opts)).is_ok_and(|s| s.is_empty())
 }
 
-fn make_clog_commit_message(from: &SemVer, to: &SemVer) -> String {
-    format!("chore: bump version {} -> {}\n\n{}", from, to, CLOG_TRAILER)
+/// The release commit message from the configured template. The trailer is
+/// always added, as it is how clog knows its own commits.
+fn make_clog_commit_message(config: &Config, from: &SemVer, to: &SemVer) -> String {
+    let message = config.release.commit_message(from, to);
+    format!("{}\n\n{}", message.trim_end(), CLOG_TRAILER)
 }
 
 #[cfg(test)]
@@ -320,7 +714,11 @@ mod test {
 
         empty_commit(
             &repo,
-            &make_clog_commit_message(&SemVer::version_0_1_0(), &SemVer::new(0, 1, 1, None, None)),
+            &make_clog_commit_message(
+                &config,
+                &SemVer::version_0_1_0(),
+                &SemVer::new(0, 1, 1, None, None),
+            ),
         )
         .unwrap();
         let commit = repo
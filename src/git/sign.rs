@@ -0,0 +1,184 @@
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use anyhow::anyhow;
+use git2::{Config as GitConfig, Repository, Signature};
+
+/// The kinds of signature git can make, from `gpg.format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    OpenPgp,
+    Ssh,
+}
+
+/// Signs release commits and tags the way git would, following
+/// `commit.gpgsign`, `tag.gpgsign`, `gpg.format` and `user.signingkey`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Signer {
+    format: Format,
+    program: String,
+    key: Option<String>,
+}
+
+impl Signer {
+    /// The signer for commits, if git is set up to sign them
+    pub fn for_commits(repo: &Repository) -> anyhow::Result<Option<Self>> {
+        Self::from_config(&repo.config()?, "commit.gpgsign")
+    }
+
+    /// The signer for tags, if git is set up to sign them
+    pub fn for_tags(repo: &Repository) -> anyhow::Result<Option<Self>> {
+        Self::from_config(&repo.config()?, "tag.gpgsign")
+    }
+
+    fn from_config(config: &GitConfig, enabled: &str) -> anyhow::Result<Option<Self>> {
+        if !config.get_bool(enabled).unwrap_or(false) {
+            return Ok(None);
+        }
+        let format = match config.get_string("gpg.format").ok().as_deref() {
+            Some("openpgp") | None => Format::OpenPgp,
+            Some("ssh") => Format::Ssh,
+            Some(other) => return Err(anyhow!("clog cannot sign with gpg.format '{}'", other)),
+        };
+        let program = match format {
+            Format::OpenPgp => config
+                .get_string("gpg.openpgp.program")
+                .or_else(|_| config.get_string("gpg.program"))
+                .unwrap_or_else(|_| "gpg".to_string()),
+            Format::Ssh => config
+                .get_string("gpg.ssh.program")
+                .unwrap_or_else(|_| "ssh-keygen".to_string()),
+        };
+        let key = config.get_string("user.signingkey").ok();
+        if format == Format::Ssh && key.is_none() {
+            return Err(anyhow!(
+                "{} is set, but user.signingkey does not name an SSH key",
+                enabled
+            ));
+        }
+        Ok(Some(Self {
+            format,
+            program,
+            key,
+        }))
+    }
+
+    /// A detached signature of `data`. Without `user.signingkey`, OpenPGP
+    /// signatures are made with the key of the `signer`, as git does.
+    pub fn sign(&self, data: &str, signer: &Signature) -> anyhow::Result<String> {
+        // a literal public key is written to a file; the agent has the
+        // private half
+        let mut key_file = None;
+        let mut command = Command::new(&self.program);
+        match self.format {
+            Format::OpenPgp => {
+                let key = match &self.key {
+                    Some(key) => key.clone(),
+                    None => format!(
+                        "{} <{}>",
+                        signer.name().unwrap_or_default(),
+                        signer.email().unwrap_or_default()
+                    ),
+                };
+                command.args(["--status-fd=2", "-bsau", &key]);
+            }
+            Format::Ssh => {
+                let key = self.key.as_deref().unwrap_or_default();
+                let literal = key
+                    .strip_prefix("key::")
+                    .or(key.starts_with("ssh-").then_some(key));
+                let path = match (literal, key.strip_prefix("~/")) {
+                    (Some(literal), _) => {
+                        let mut file = tempfile::NamedTempFile::new()?;
+                        writeln!(file, "{}", literal)?;
+                        key_file.insert(file).path().to_path_buf()
+                    }
+                    (None, Some(rest)) => {
+                        PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(rest)
+                    }
+                    (None, None) => PathBuf::from(key),
+                };
+                command.args(["-Y", "sign", "-n", "git", "-f"]).arg(path);
+            }
+        }
+
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("Failed to launch signing program '{}': {}", self.program, e))?;
+        child.stdin.take().unwrap().write_all(data.as_bytes())?;
+        let output = child.wait_with_output()?;
+        drop(key_file);
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Signing with '{}' failed: {}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let signature = String::from_utf8(output.stdout)?;
+        if signature.trim().is_empty() {
+            return Err(anyhow!("'{}' did not write a signature", self.program));
+        }
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(entries: &[(&str, &str)]) -> (tempfile::TempDir, GitConfig) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = GitConfig::open(&dir.path().join("config")).unwrap();
+        for (name, value) in entries {
+            config.set_str(name, value).unwrap();
+        }
+        (dir, config)
+    }
+
+    #[test]
+    fn test_signer_from_config() {
+        let (_dir, git) = config(&[]);
+        assert_eq!(Signer::from_config(&git, "commit.gpgsign").unwrap(), None);
+
+        let (_dir, git) = config(&[("commit.gpgsign", "true"), ("gpg.program", "gpg2")]);
+        assert_eq!(
+            Signer::from_config(&git, "commit.gpgsign").unwrap(),
+            Some(Signer {
+                format: Format::OpenPgp,
+                program: "gpg2".to_string(),
+                key: None,
+            })
+        );
+
+        let (_dir, git) = config(&[
+            ("tag.gpgsign", "true"),
+            ("gpg.format", "ssh"),
+            ("user.signingkey", "~/.ssh/id_ed25519"),
+        ]);
+        assert_eq!(Signer::from_config(&git, "commit.gpgsign").unwrap(), None);
+        assert_eq!(
+            Signer::from_config(&git, "tag.gpgsign").unwrap(),
+            Some(Signer {
+                format: Format::Ssh,
+                program: "ssh-keygen".to_string(),
+                key: Some("~/.ssh/id_ed25519".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_signer_needs_a_known_format_and_key() {
+        let (_dir, git) = config(&[("commit.gpgsign", "true"), ("gpg.format", "x509")]);
+        assert!(Signer::from_config(&git, "commit.gpgsign").is_err());
+
+        let (_dir, git) = config(&[("commit.gpgsign", "true"), ("gpg.format", "ssh")]);
+        assert!(Signer::from_config(&git, "commit.gpgsign").is_err());
+    }
+}
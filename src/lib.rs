@@ -1,8 +1,15 @@
 mod changelog;
+mod convention;
+mod dependencies;
+pub mod fragment;
 pub mod git;
+mod links;
 mod python;
+mod release;
+mod rpm;
 mod rust;
 pub mod semver;
+mod summarizer;
 
 use std::{
     io::Write,
@@ -22,11 +29,16 @@ pub mod test_support;
 pub use crate::git::is_repo_ready;
 
 use crate::{
+    convention::Convention,
+    dependencies::DependencyUpdates,
+    fragment::Fragment,
     git::{
         create_clog_commit, generate_diff_for_window, remove_last_release_commit, CommitWrapper,
         GitHistory,
     },
+    links::Links,
     python::PyProject,
+    rpm::SpecProject,
     rust::CargoProject,
     semver::{SemVer, SemVerBump},
 };
@@ -38,11 +50,23 @@ pub fn bump_project_version(
     repo: &Repository,
     project: &mut dyn Project,
     config: &Config,
+) -> anyhow::Result<()> {
+    let fragments = fragment::pending(project.get_dir());
+    bump_project_version_with_fragments(repo, project, &fragments, config)
+}
+
+/// Like [`bump_project_version`], releasing the given pending `fragments`
+pub fn bump_project_version_with_fragments(
+    repo: &Repository,
+    project: &mut dyn Project,
+    fragments: &[Fragment],
+    config: &Config,
 ) -> anyhow::Result<()> {
     let history: Vec<CommitWrapper> = GitHistory::new(project, repo).collect();
-    changelog::prepare_changelog(history.clone().into_iter(), Some(repo), project, config)?;
+    let next_version = get_release_version(history.clone().into_iter(), fragments, config);
+    changelog::prepare_changelog(history.into_iter(), Some(repo), project, fragments, config)?;
 
-    let next_version = match get_next_version(history.into_iter(), config) {
+    let next_version = match next_version {
         Some(v) => v,
         None => return Ok(()),
     };
@@ -57,7 +81,8 @@ pub fn make_stable_release(
     config: &Config,
 ) -> anyhow::Result<()> {
     let history: Vec<CommitWrapper> = GitHistory::new(project, repo).collect();
-    changelog::prepare_changelog(history.clone().into_iter(), Some(repo), project, config)?;
+    let fragments = fragment::pending(project.get_dir());
+    changelog::prepare_changelog(history.into_iter(), Some(repo), project, &fragments, config)?;
     project.set_initial_release()?;
     project.update_project_file()?;
     create_clog_commit(repo, project, config, SemVer::version_1_0_0())
@@ -101,8 +126,101 @@ pub fn preview_release(repo: &Repository, config: &Config) -> anyhow::Result<()>
     Ok(())
 }
 
+/// The changes `clog notes` describes
+pub enum NotesSelection {
+    /// The unreleased changes, or else the newest release
+    Latest,
+    Version(SemVer),
+    /// The commits reachable from `to` but not from `from`
+    Range {
+        from: Option<String>,
+        to: String,
+    },
+}
+
+/// Release notes rendered like a section of the changelog. Nothing is
+/// written, so the repo does not need to be clean.
+pub fn release_notes(
+    repo: &Repository,
+    config: &Config,
+    selection: NotesSelection,
+) -> anyhow::Result<String> {
+    let project = detect_project(config)?;
+    let history = || -> Vec<CommitWrapper> { GitHistory::new(project.as_ref(), repo).collect() };
+    // fragments only belong to the unreleased changes, not to a range
+    let fragments = || fragment::pending(project.get_dir());
+    match selection {
+        NotesSelection::Latest => changelog::version_notes(
+            history().into_iter(),
+            None,
+            &fragments(),
+            Some(repo),
+            project.as_ref(),
+            config,
+        ),
+        NotesSelection::Version(version) => changelog::version_notes(
+            history().into_iter(),
+            Some(&version),
+            &fragments(),
+            Some(repo),
+            project.as_ref(),
+            config,
+        ),
+        NotesSelection::Range { from, to } => {
+            let resolve = |rev: &str| -> anyhow::Result<Oid> {
+                Ok(repo.revparse_single(rev)?.peel_to_commit()?.id())
+            };
+            let from = from.as_deref().map(resolve).transpose()?;
+            let to = resolve(&to)?;
+            let range: Vec<CommitWrapper> =
+                GitHistory::between(project.as_ref(), repo, from, to)?.collect();
+            let earlier: Vec<CommitWrapper> = match from {
+                Some(from) => GitHistory::between(project.as_ref(), repo, None, from)?.collect(),
+                None => vec![],
+            };
+            changelog::range_notes(
+                range,
+                earlier.into_iter(),
+                Some(repo),
+                project.as_ref(),
+                config,
+            )
+        }
+    }
+}
+
+/// Rewrite the project's changelog in the configured format
+pub fn reformat_changelog(config: &Config) -> anyhow::Result<()> {
+    let project = detect_project(config)?;
+    changelog::reformat_changelog(project.as_ref(), config)
+}
+
+/// Delete the summaries cached in the repo
+pub fn clear_summarizer_cache(repo: &Repository) -> anyhow::Result<()> {
+    summarizer::SummaryCache::new(repo).clear()
+}
+
+/// Merge both sides of the conflicts in the project's changelog
+pub fn resolve_changelog(config: &Config) -> anyhow::Result<()> {
+    let project = detect_project(config)?;
+    changelog::resolve_changelog(project.as_ref(), config)
+}
+
+/// Merge two versions of a changelog into `ours`, for the clog merge driver
+pub fn merge_changelog_files(
+    ours: &Path,
+    theirs: &Path,
+    base: Option<&Path>,
+    config: &Config,
+) -> anyhow::Result<()> {
+    changelog::merge_changelog_files(ours, theirs, base, config)
+}
+
 static DEFAULT_PATTERNS: Lazy<Patterns> = Lazy::new(|| Patterns {
-    major: vec![Regex::new(r"^.*!:").unwrap()],
+    major: vec![
+        Regex::new(r"^.*!:").unwrap(),
+        Regex::new(convention::BREAKING_FOOTER).unwrap(),
+    ],
     minor: vec![Regex::new(r"^feat:").unwrap()],
     patch: vec![Regex::new(r"^fix:").unwrap()],
 });
@@ -122,6 +240,10 @@ pub trait Project {
     fn get_changelog(&self) -> &Path {
         Path::new("Changelog.md")
     }
+    /// RPM spec file whose `%changelog` should follow the project's changelog
+    fn get_spec_file(&self) -> Option<&Path> {
+        None
+    }
 }
 
 pub struct Config {
@@ -130,6 +252,37 @@ pub struct Config {
     name: String,
     email: String,
     pub summarizer_command: Option<String>,
+    /// The `[summarizer]` table
+    summarizer: summarizer::Settings,
+    /// Template file for the summarizer prompt
+    summarizer_prompt: Option<PathBuf>,
+    /// Pathspecs of files the summarizer does not see the diff of
+    summarizer_exclude: Vec<String>,
+    /// Bytes of diff in each summarizer prompt
+    summarizer_diff_budget: usize,
+    /// How many releases are summarised at once when making a whole changelog
+    summarizer_concurrency: usize,
+    pub rpm_spec: Option<PathBuf>,
+    pub release_notes_json: Option<PathBuf>,
+    changelog_format: ChangelogFormat,
+    /// Open new changelog sections in an editor before they are written
+    pub edit_changelog: bool,
+    /// Reuse summaries made for the same commits and prompt
+    pub summarizer_cache: bool,
+    links: Option<Links>,
+    contributors: bool,
+    /// Start releases with a block of notes on their breaking changes
+    breaking_changes: bool,
+    /// The `[release]` table
+    release: release::Settings,
+    /// Drop the commit type from entries and tidy up their wording
+    clean_entries: bool,
+    /// Commits to list in the changelog, whether or not they bump the version
+    include: Vec<IncludeRule>,
+    /// Fold dependency bot commits into one section
+    dependencies: Option<DependencyUpdates>,
+    /// List the packages changed in lockfiles
+    lockfile_changes: bool,
 }
 
 impl Config {
@@ -143,10 +296,106 @@ impl Config {
             if let Some(cmd) = overrides.get("summarizer_command").and_then(|v| v.as_str()) {
                 config.summarizer_command = Some(cmd.to_string());
             }
+            if let Some(table) = overrides.get("summarizer").and_then(|v| v.as_table()) {
+                config.summarizer = summarizer::Settings::from_toml(table);
+            }
+            if let Some(prompt) = overrides.get("summarizer_prompt").and_then(|v| v.as_str()) {
+                config.summarizer_prompt = Some(PathBuf::from(prompt));
+            }
+            if let Some(exclude) = overrides
+                .get("summarizer_exclude")
+                .and_then(|v| v.as_array())
+            {
+                config.summarizer_exclude = exclude
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .map(String::from)
+                    .collect();
+            }
+            if let Some(budget) = overrides
+                .get("summarizer_diff_budget")
+                .and_then(|v| v.as_integer())
+            {
+                config.summarizer_diff_budget = budget.max(0) as usize;
+            }
+            if let Some(concurrency) = overrides
+                .get("summarizer_concurrency")
+                .and_then(|v| v.as_integer())
+            {
+                config.summarizer_concurrency = concurrency.max(1) as usize;
+            }
+            if let Some(spec) = overrides.get("rpm_spec").and_then(|v| v.as_str()) {
+                config.rpm_spec = Some(PathBuf::from(spec));
+            }
+            if let Some(json) = overrides.get("release_notes_json").and_then(|v| v.as_str()) {
+                config.release_notes_json = Some(PathBuf::from(json));
+            }
+            if let Some(format) = overrides.get("changelog_format").and_then(|v| v.as_str()) {
+                match format {
+                    "clog" => config.changelog_format = ChangelogFormat::Clog,
+                    "keep-a-changelog" => {
+                        config.changelog_format = ChangelogFormat::KeepAChangelog
+                    }
+                    other => eprintln!(
+                        "Unknown changelog_format '{}', expected \"clog\" or \"keep-a-changelog\"; using clog",
+                        other
+                    ),
+                }
+            }
+            if let Some(links) = overrides.get("links").and_then(|v| v.as_table()) {
+                config.links = Some(Links::from_toml(links));
+            }
+            if let Some(table) = overrides.get("release").and_then(|v| v.as_table()) {
+                config.release = release::Settings::from_toml(table);
+            }
+            if let Some(contributors) = overrides.get("contributors").and_then(|v| v.as_bool()) {
+                config.contributors = contributors;
+            }
+            if let Some(breaking) = overrides.get("breaking_changes").and_then(|v| v.as_bool()) {
+                config.breaking_changes = breaking;
+            }
+            if let Some(clean) = overrides.get("clean_entries").and_then(|v| v.as_bool()) {
+                config.clean_entries = clean;
+            }
+            let convention = match overrides.get("convention").and_then(|v| v.as_str()) {
+                Some(name) => Convention::from_name(name).unwrap_or_else(|| {
+                    eprintln!("Unknown convention '{}', using conventional", name);
+                    Convention::default()
+                }),
+                None => Convention::default(),
+            };
+            config.patterns = convention.patterns();
+            if let Some(patterns) = overrides.get("patterns").and_then(|v| v.as_table()) {
+                config.patterns.extend_from_toml(patterns);
+            }
+            // custom rules come first so they win over the preset's sections
+            if let Some(rules) = overrides.get("include").and_then(|v| v.as_array()) {
+                config.include = rules
+                    .iter()
+                    .filter_map(|v| v.as_table())
+                    .filter_map(IncludeRule::from_toml)
+                    .collect();
+            }
+            config.include.extend(convention.include_rules());
+            if let Some(deps) = overrides.get("dependencies").and_then(|v| v.as_table()) {
+                config.dependencies = Some(DependencyUpdates::from_toml(deps));
+            }
+            if let Some(lockfiles) = overrides.get("lockfile_changes").and_then(|v| v.as_bool()) {
+                config.lockfile_changes = lockfiles;
+            }
         }
         config
     }
 
+    /// The summarizer to write changelog entries with, if there is one
+    fn summarizer_backend(&self) -> Option<summarizer::Backend> {
+        match (&self.summarizer.http, &self.summarizer_command) {
+            (Some(http), _) => Some(summarizer::Backend::Http(http.clone())),
+            (None, Some(command)) => Some(summarizer::Backend::Command(command.clone())),
+            (None, None) => None,
+        }
+    }
+
     fn load_toml(path: &std::path::Path) -> Option<Table> {
         let content = std::fs::read_to_string(path.join("clog.toml")).ok()?;
         content.parse::<Table>().ok()
@@ -162,10 +411,38 @@ impl Default for Config {
             name: "clog-bot".to_string(),
             email: "clog-bot@local".to_string(),
             summarizer_command: None,
+            summarizer: summarizer::Settings::default(),
+            summarizer_prompt: None,
+            summarizer_exclude: vec![],
+            summarizer_diff_budget: summarizer::DEFAULT_DIFF_BUDGET,
+            summarizer_concurrency: 1,
+            rpm_spec: None,
+            release_notes_json: None,
+            changelog_format: ChangelogFormat::default(),
+            edit_changelog: false,
+            summarizer_cache: true,
+            links: None,
+            contributors: false,
+            breaking_changes: false,
+            release: release::Settings::default(),
+            clean_entries: false,
+            include: vec![],
+            dependencies: None,
+            lockfile_changes: false,
         }
     }
 }
 
+/// How the Markdown changelog is laid out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChangelogFormat {
+    /// `# Version x.y.z` headings followed by a list of entries
+    #[default]
+    Clog,
+    /// <https://keepachangelog.com>
+    KeepAChangelog,
+}
+
 #[derive(Debug, Clone)]
 pub struct Patterns {
     major: Vec<Regex>,
@@ -179,6 +456,61 @@ impl Default for Patterns {
     }
 }
 
+impl Patterns {
+    /// Add the `major`, `minor` and `patch` lists of a `[patterns]` table
+    fn extend_from_toml(&mut self, table: &Table) {
+        for (level, patterns) in [
+            ("major", &mut self.major),
+            ("minor", &mut self.minor),
+            ("patch", &mut self.patch),
+        ] {
+            for pattern in table
+                .get(level)
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str())
+            {
+                match Regex::new(pattern) {
+                    Ok(regex) => patterns.push(regex),
+                    Err(e) => eprintln!("Ignoring {} pattern '{}': {}", level, pattern, e),
+                }
+            }
+        }
+    }
+}
+
+/// Lists the commits matching `pattern` in the changelog, under `section`
+/// if it has one
+#[derive(Debug, Clone)]
+pub struct IncludeRule {
+    pattern: Regex,
+    section: Option<String>,
+}
+
+impl IncludeRule {
+    fn from_toml(table: &Table) -> Option<Self> {
+        let Some(pattern) = table.get("pattern").and_then(|v| v.as_str()) else {
+            eprintln!("Ignoring include rule without a pattern");
+            return None;
+        };
+        // case is ignored, as in the presets' rules
+        match Regex::new(&format!("(?i){pattern}")) {
+            Ok(regex) => Some(Self {
+                pattern: regex,
+                section: table
+                    .get("section")
+                    .and_then(|v| v.as_str())
+                    .map(String::from),
+            }),
+            Err(e) => {
+                eprintln!("Ignoring include rule '{}': {}", pattern, e);
+                None
+            }
+        }
+    }
+}
+
 pub trait HistoryItem {
     fn message(&self) -> String;
     fn version(&self) -> SemVer;
@@ -186,6 +518,20 @@ pub trait HistoryItem {
     fn commit_id(&self) -> Option<Oid> {
         None
     }
+    /// Unix time the commit was made
+    fn time(&self) -> Option<i64> {
+        None
+    }
+    /// Everyone credited with the commit
+    fn authors(&self) -> Vec<Person> {
+        vec![]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Person {
+    pub name: String,
+    pub email: String,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -239,6 +585,20 @@ where
 }
 
 pub fn get_next_version<I, H>(history: I, config: &Config) -> Option<SemVer>
+where
+    I: Iterator<Item = H>,
+    H: HistoryItem,
+{
+    get_release_version(history, &[], config)
+}
+
+/// The version of the release being made, counting the bumps of the
+/// `fragments` waiting to go in it as well as the commits
+pub fn get_release_version<I, H>(
+    history: I,
+    fragments: &[Fragment],
+    config: &Config,
+) -> Option<SemVer>
 where
     I: Iterator<Item = H>,
     H: HistoryItem,
@@ -248,7 +608,8 @@ where
     let version = commits.first()?.version();
     let bump = commits
         .iter()
-        .map(|c| parse_commit_message(&c.message(), config))
+        .map(|c| commit_bump(c, config))
+        .chain(fragments.iter().map(|f| f.bump))
         .max()?;
     if bump == SemVerBump::None {
         return None;
@@ -261,11 +622,32 @@ pub fn detect_project(config: &Config) -> anyhow::Result<Box<dyn Project>> {
         Ok(Box::new(CargoProject::from_dir(&config.path)?))
     } else if config.path.join("pyproject.toml").exists() {
         Ok(Box::new(PyProject::from_dir(&config.path)?))
+    } else if SpecProject::find_spec_file(&config.path).is_some() {
+        Ok(Box::new(SpecProject::from_dir(&config.path)?))
     } else {
         Err(anyhow::anyhow!("No supported project file found"))
     }
 }
 
+/// The bump for a commit, where dependency updates get their configured bump
+/// unless a trailer says otherwise. Release commits never bump, whatever
+/// their message template says.
+fn commit_bump<H: HistoryItem>(commit: &H, config: &Config) -> SemVerBump {
+    if commit.kind() == HistoryItemKind::ClogBump {
+        return SemVerBump::None;
+    }
+    let message = commit.message();
+    match &config.dependencies {
+        Some(deps)
+            if get_bump_from_trailer(&message) == SemVerBump::None
+                && deps.is_update(&message, &commit.authors()) =>
+        {
+            deps.bump
+        }
+        _ => parse_commit_message(&message, config),
+    }
+}
+
 fn parse_commit_message(commit_message: &str, config: &Config) -> SemVerBump {
     let bump = get_bump_from_trailer(commit_message);
     if bump != SemVerBump::None {
@@ -387,6 +769,17 @@ mod test {
         tmp_dir
     }
 
+    #[test]
+    fn test_include_rule_ignores_case() {
+        let table: Table = "pattern = \"^perf\"\nsection = \"Performance\""
+            .parse()
+            .unwrap();
+        let rule = IncludeRule::from_toml(&table).unwrap();
+        assert!(rule.pattern.is_match("Perf: faster startup"));
+        assert!(rule.pattern.is_match("perf: faster startup"));
+        assert!(!rule.pattern.is_match("fix: perf regression"));
+    }
+
     fn test_bump_helper(dir: &TempDir, repo: &Repository) {
         let config = Config::new(dir);
         let mut project = detect_project(&config).unwrap();
@@ -696,4 +1089,28 @@ mod test {
         let config = Config::default();
         assert_eq!(bump, parse_commit_message(message, &config))
     }
+
+    #[rstest]
+    #[case::patch(SemVerBump::Patch, Some(SemVer::new(0, 1, 1, None, None)))]
+    #[case::none(SemVerBump::None, None)]
+    fn test_dependency_update_bump(#[case] bump: SemVerBump, #[case] expected: Option<SemVer>) {
+        let history = vec![
+            TestCommitWrapper::new_normal(
+                "Bump serde from 1.0.1 to 1.0.2",
+                SemVer::new(0, 1, 0, None, None),
+            )
+            .with_author("dependabot[bot]", "support@github.com"),
+            TestCommitWrapper::new_normal(
+                "chore(deps): update rust crate toml to v0.9.8",
+                SemVer::new(0, 1, 0, None, None),
+            ),
+        ];
+        let mut deps = DependencyUpdates::default();
+        deps.bump = bump;
+        let config = Config {
+            dependencies: Some(deps),
+            ..Default::default()
+        };
+        assert_eq!(get_next_version(history.into_iter(), &config), expected);
+    }
 }
@@ -0,0 +1,193 @@
+use git2::Oid;
+use regex::{Captures, Regex};
+use toml::Table;
+
+/// Turns issue, pull request and commit references in changelog entries
+/// into Markdown links.
+#[derive(Debug, Clone, Default)]
+pub struct Links {
+    repository: Option<String>,
+    commit_hashes: bool,
+    references: Vec<Reference>,
+}
+
+/// A pattern for a reference and the URL it links to. `{0}` in the URL is
+/// replaced with the whole match, `{1}`, `{2}`, ... with capture groups and
+/// `{repository}` with the repository URL. If the pattern has a group named
+/// `ref`, only that part of the match becomes the link text.
+#[derive(Debug, Clone)]
+struct Reference {
+    pattern: Regex,
+    url: String,
+}
+
+impl Reference {
+    fn new(pattern: &str, url: &str) -> Self {
+        Self {
+            pattern: Regex::new(pattern).unwrap(),
+            url: url.to_string(),
+        }
+    }
+}
+
+impl Links {
+    pub fn from_toml(table: &Table) -> Self {
+        let repository = table
+            .get("repository")
+            .and_then(|v| v.as_str())
+            .map(|r| r.trim_end_matches('/').to_string());
+        let commit_hashes = table
+            .get("commit_hashes")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        let mut references = vec![];
+        for reference in table
+            .get("references")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_table())
+        {
+            let (Some(pattern), Some(url)) = (
+                reference.get("pattern").and_then(|v| v.as_str()),
+                reference.get("url").and_then(|v| v.as_str()),
+            ) else {
+                eprintln!("Ignoring link reference without a pattern and url");
+                continue;
+            };
+            match Regex::new(pattern) {
+                Ok(pattern) => references.push(Reference {
+                    pattern,
+                    url: url.to_string(),
+                }),
+                Err(e) => eprintln!("Ignoring link reference '{}': {}", pattern, e),
+            }
+        }
+        if repository.is_some() {
+            // squash merges end with the pull request number
+            references.push(Reference::new(
+                r"\((?P<ref>#(\d+))\)\s*$",
+                "{repository}/pull/{2}",
+            ));
+            references.push(Reference::new(r"\B#(\d+)\b", "{repository}/issues/{1}"));
+            references.push(Reference::new(r"\bGH-(\d+)\b", "{repository}/issues/{1}"));
+        }
+
+        Self {
+            repository,
+            commit_hashes,
+            references,
+        }
+    }
+
+    /// Link the references in an entry, and add the commit it came from
+    pub fn linkify(&self, text: &str, commit: Option<Oid>) -> String {
+        let mut spans: Vec<(usize, usize, String)> = vec![];
+        for reference in &self.references {
+            for caps in reference.pattern.captures_iter(text) {
+                let link = caps.name("ref").unwrap_or_else(|| caps.get(0).unwrap());
+                if spans
+                    .iter()
+                    .any(|(start, end, _)| link.start() < *end && *start < link.end())
+                {
+                    continue;
+                }
+                let url = self.expand(&reference.url, &caps);
+                spans.push((link.start(), link.end(), url));
+            }
+        }
+        spans.sort_by_key(|(start, _, _)| *start);
+
+        let mut linked = String::new();
+        let mut last = 0;
+        for (start, end, url) in spans {
+            linked.push_str(&text[last..start]);
+            linked.push_str(&format!("[{}]({})", &text[start..end], url));
+            last = end;
+        }
+        linked.push_str(&text[last..]);
+
+        if let (true, Some(commit)) = (self.commit_hashes, commit) {
+            let short = &commit.to_string()[..7];
+            match &self.repository {
+                Some(repository) => {
+                    linked.push_str(&format!(" ([{}]({}/commit/{}))", short, repository, commit))
+                }
+                None => linked.push_str(&format!(" ({})", short)),
+            }
+        }
+        linked
+    }
+
+    fn expand(&self, template: &str, caps: &Captures) -> String {
+        let mut url = template.replace("{repository}", self.repository.as_deref().unwrap_or(""));
+        for i in 0..caps.len() {
+            let group = caps.get(i).map_or("", |m| m.as_str());
+            url = url.replace(&format!("{{{}}}", i), group);
+        }
+        url
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use git2::Oid;
+    use rstest::rstest;
+
+    use super::*;
+
+    const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    fn links(toml: &str) -> Links {
+        Links::from_toml(&toml.parse::<Table>().unwrap())
+    }
+
+    #[rstest]
+    #[case::issue(
+        "fix: crash on start #42",
+        "fix: crash on start [#42](https://github.com/o/r/issues/42)"
+    )]
+    #[case::gh(
+        "fix: crash, see GH-7",
+        "fix: crash, see [GH-7](https://github.com/o/r/issues/7)"
+    )]
+    #[case::squash(
+        "feat: add thing (#12)",
+        "feat: add thing ([#12](https://github.com/o/r/pull/12))"
+    )]
+    #[case::custom(
+        "fix: PROJ-456 and #3",
+        "fix: [PROJ-456](https://jira.example.com/browse/PROJ-456) and [#3](https://github.com/o/r/issues/3)"
+    )]
+    #[case::nothing("chore: tidy up", "chore: tidy up")]
+    #[case::anchor_is_not_an_issue("docs: see Readme.md#123", "docs: see Readme.md#123")]
+    fn test_linkify_references(#[case] text: &str, #[case] expected: &str) {
+        let links = links(
+            r#"
+            repository = "https://github.com/o/r/"
+            commit_hashes = false
+            [[references]]
+            pattern = "PROJ-\\d+"
+            url = "https://jira.example.com/browse/{0}"
+            "#,
+        );
+        assert_eq!(links.linkify(text, None), expected);
+    }
+
+    #[test]
+    fn test_linkify_commit() {
+        let commit = Some(Oid::from_str(COMMIT).unwrap());
+        let with_repo = links(r#"repository = "https://github.com/o/r""#);
+        assert_eq!(
+            with_repo.linkify("fix: a bug", commit),
+            format!("fix: a bug ([0123456](https://github.com/o/r/commit/{COMMIT}))")
+        );
+        let without_repo = links("");
+        assert_eq!(
+            without_repo.linkify("fix: a bug", commit),
+            "fix: a bug (0123456)"
+        );
+        assert_eq!(without_repo.linkify("fix: a bug", None), "fix: a bug");
+    }
+}
@@ -1,10 +1,17 @@
-use std::{fs, io::Write, path::Path};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, Context, Error};
-use clap::{Parser, Subcommand};
+use clap::{error::ErrorKind, Args, CommandFactory, Parser, Subcommand};
 use clog::{
-    bump_project_version, detect_project, get_next_version, git::GitHistory, is_repo_ready,
-    make_stable_release, semver::SemVer, Config,
+    bump_project_version_with_fragments, detect_project, fragment, get_release_version,
+    git::{repo_has_commits, GitHistory},
+    is_repo_ready, make_stable_release,
+    semver::SemVer,
+    Config, NotesSelection,
 };
 use git2::Repository;
 use inquire::Confirm;
@@ -18,43 +25,195 @@ struct Cli {
     /// Skip confirmation prompts (automatically answer yes)
     #[arg(short = 'y', long, global = true)]
     yes: bool,
+
+    #[command(flatten)]
+    release: ReleaseArgs,
+
+    /// Run the summarizer again rather than reusing earlier summaries
+    #[arg(long, global = true)]
+    no_cache: bool,
 }
 
-#[derive(Default, Subcommand)]
+#[derive(Subcommand)]
 enum Commands {
-    #[default]
-    Bump,
-    Redo,
-    Stable,
+    Bump(ReleaseArgs),
+    Redo(ReleaseArgs),
+    Stable(ReleaseArgs),
     InstallAliases,
     Preview,
+    /// Work with an existing changelog
+    #[command(subcommand)]
+    Changelog(ChangelogCommands),
+    /// Print the release notes for a version or a range of commits
+    Notes(NotesArgs),
+    /// Write changelog entries by hand for the next release
+    #[command(subcommand)]
+    Fragment(FragmentCommands),
+    /// Manage the summaries cached in the repo
+    #[command(subcommand)]
+    Cache(CacheCommands),
+}
+
+#[derive(Args, Clone, Copy, Default)]
+struct ReleaseArgs {
+    /// Edit the new changelog section in your editor before releasing
+    #[arg(short = 'e', long)]
+    edit: bool,
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Delete every cached summary
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum FragmentCommands {
+    /// Add a fragment to the `.changes` directory
+    Add(FragmentArgs),
+}
+
+#[derive(Args)]
+struct FragmentArgs {
+    /// How much the change bumps the version
+    #[arg(long, default_value = "none", value_parser = ["none", "patch", "minor", "major"])]
+    bump: String,
+
+    /// The changelog section to list the entry under
+    #[arg(long)]
+    category: Option<String>,
+
+    /// Markdown text of the entry [default: written in your editor]
+    text: Option<String>,
+}
+
+#[derive(Args)]
+struct NotesArgs {
+    /// Version to describe [default: the unreleased changes, or else the newest release]
+    #[arg(conflicts_with_all = ["from", "to"])]
+    version: Option<String>,
+
+    /// Describe the commits after this revision, such as a tag
+    #[arg(long)]
+    from: Option<String>,
+
+    /// Describe the commits up to this revision [default: HEAD]
+    #[arg(long)]
+    to: Option<String>,
+
+    /// Write the notes to a file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum ChangelogCommands {
+    /// Render the changelog again in the format set in clog.toml
+    Reformat,
+    /// Resolve merge conflicts in the changelog by merging both sides by version
+    Resolve,
+    /// Merge two versions of the changelog into the first, as a git merge driver
+    Merge {
+        /// Our version, which is overwritten with the result
+        ours: PathBuf,
+        /// Their version
+        theirs: PathBuf,
+        /// The version both came from, to leave out what either side removed
+        base: Option<PathBuf>,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
     let current_dir = Path::new("./");
-    let config = Config::new(&current_dir);
+    let mut config = Config::new(&current_dir);
+    config.summarizer_cache = !cli.no_cache;
     let repo = Repository::open(current_dir)
         .with_context(|| format!("Failed to open repo at {:?}", current_dir.canonicalize()))?;
 
-    if !is_repo_ready(&repo) {
+    let command = cli.command.unwrap_or(Commands::Bump(cli.release));
+    match &command {
+        Commands::Bump(args) | Commands::Redo(args) | Commands::Stable(args) => {
+            config.edit_changelog = cli.release.edit || args.edit;
+        }
+        _ if cli.release.edit => Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--edit can only be used with bump, redo and stable",
+            )
+            .exit(),
+        _ => {}
+    }
+    // notes and the cache leave the work tree alone, fragments are committed
+    // with the change they describe, and changelogs are merged part way
+    // through a merge
+    if let Commands::Notes(_)
+    | Commands::Fragment(_)
+    | Commands::Cache(_)
+    | Commands::Changelog(ChangelogCommands::Resolve | ChangelogCommands::Merge { .. }) = command
+    {
+        if !repo_has_commits(&repo) {
+            return Err(anyhow!("Repo has no commits"));
+        }
+    } else if !is_repo_ready(&repo) {
         return Err(anyhow!("Repo is not in a clean state. Commit your changes"));
     }
 
-    match cli.command.unwrap_or_default() {
-        Commands::Bump => bump_release(&repo, &config, cli.yes),
-        Commands::Redo => redo_release(&repo, &config, cli.yes),
-        Commands::Stable => major_version_one(&repo, &config, cli.yes),
-        Commands::InstallAliases => install_aliases(current_dir),
+    match command {
+        Commands::Bump(_) => bump_release(&repo, &config, cli.yes),
+        Commands::Redo(_) => redo_release(&repo, &config, cli.yes),
+        Commands::Stable(_) => major_version_one(&repo, &config, cli.yes),
+        Commands::InstallAliases => install_aliases(current_dir, &config),
         Commands::Preview => clog::preview_release(&repo, &config),
+        Commands::Changelog(ChangelogCommands::Reformat) => clog::reformat_changelog(&config),
+        Commands::Changelog(ChangelogCommands::Resolve) => clog::resolve_changelog(&config),
+        Commands::Changelog(ChangelogCommands::Merge { ours, theirs, base }) => {
+            clog::merge_changelog_files(&ours, &theirs, base.as_deref(), &config)
+        }
+        Commands::Notes(args) => release_notes(&repo, &config, args),
+        Commands::Fragment(FragmentCommands::Add(args)) => add_fragment(&repo, &config, args),
+        Commands::Cache(CacheCommands::Clear) => clog::clear_summarizer_cache(&repo),
+    }
+}
+
+fn add_fragment(repo: &Repository, config: &Config, args: FragmentArgs) -> anyhow::Result<()> {
+    let bump = fragment::parse_bump(&args.bump).expect("clap checks the bump level");
+    let path = fragment::add(
+        Some(repo),
+        config,
+        bump,
+        args.category.as_deref(),
+        args.text.as_deref(),
+    )?;
+    println!("Added {}", path.display());
+    Ok(())
+}
+
+fn release_notes(repo: &Repository, config: &Config, args: NotesArgs) -> anyhow::Result<()> {
+    let selection = match (args.version, args.from, args.to) {
+        (Some(version), _, _) => {
+            NotesSelection::Version(SemVer::parse(version.trim_start_matches('v'))?)
+        }
+        (None, None, None) => NotesSelection::Latest,
+        (None, from, to) => NotesSelection::Range {
+            from,
+            to: to.unwrap_or_else(|| "HEAD".to_string()),
+        },
+    };
+    let notes = clog::release_notes(repo, config, selection)?;
+    match args.output {
+        Some(path) => fs::write(path, notes)?,
+        None => print!("{}", notes),
     }
+    Ok(())
 }
 
 fn bump_release(repo: &Repository, config: &Config, auto_yes: bool) -> anyhow::Result<()> {
     let mut project = detect_project(config)?;
     let current_version = project.get_version().clone();
     let history = GitHistory::new(project.as_ref(), repo);
-    let new_version = match get_next_version(history, config) {
+    let fragments = fragment::pending(project.get_dir());
+    let new_version = match get_release_version(history, &fragments, config) {
         Some(v) => v,
         None => current_version.clone(),
     };
@@ -79,7 +238,7 @@ fn bump_release(repo: &Repository, config: &Config, auto_yes: bool) -> anyhow::R
         };
 
         if should_bump {
-            bump_project_version(repo, project.as_mut(), config)?;
+            bump_project_version_with_fragments(repo, project.as_mut(), &fragments, config)?;
         }
     } else {
         println!("No release required")
@@ -146,7 +305,7 @@ fn redo_release(repo: &Repository, config: &Config, auto_yes: bool) -> anyhow::R
     Ok(())
 }
 
-pub fn install_aliases(repo_root: &Path) -> anyhow::Result<()> {
+pub fn install_aliases(repo_root: &Path, config: &Config) -> anyhow::Result<()> {
     let git_config = include_str!("./static/.gitconfig.template");
     let prepare_commit_msg = include_str!("./static/.prepare-commit-msg.template");
 
@@ -169,5 +328,24 @@ pub fn install_aliases(repo_root: &Path) -> anyhow::Result<()> {
         .open(&hook_path)?
         .write_all(prepare_commit_msg.as_bytes())?;
 
+    // merge the changelog with the driver set up in the git config
+    let changelog = detect_project(config)
+        .map(|p| p.get_changelog().to_path_buf())
+        .unwrap_or_else(|_| PathBuf::from("Changelog.md"));
+    let attribute = format!("{} merge=clog\n", changelog.display());
+    let attributes_path = repo_root.join(".gitattributes");
+    let attributes = fs::read_to_string(&attributes_path).unwrap_or_default();
+    if !attributes.lines().any(|l| l == attribute.trim_end()) {
+        let separator = if attributes.is_empty() || attributes.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        fs::write(
+            attributes_path,
+            format!("{attributes}{separator}{attribute}"),
+        )?;
+    }
+
     Ok(())
 }
@@ -0,0 +1,137 @@
+use toml::Table;
+
+use crate::semver::SemVer;
+
+pub const DEFAULT_TAG_NAME: &str = "v{version}";
+pub const DEFAULT_TAG_MESSAGE: &str = "Release {version}";
+pub const DEFAULT_COMMIT_MESSAGE: &str = "chore: bump version {previous_version} -> {version}";
+
+/// What kind of tag marks a release
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagType {
+    /// A tag object with a tagger, date and message, which can be signed
+    #[default]
+    Annotated,
+    /// Just a reference to the release commit
+    Lightweight,
+    /// No tag at all
+    None,
+}
+
+/// The `[release]` table in `clog.toml`: how the release commit and tag are
+/// written. `{version}` and `{previous_version}` in the templates are
+/// replaced with the versions released.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub tag_name: String,
+    pub tag_type: TagType,
+    pub tag_message: String,
+    pub commit_message: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            tag_name: DEFAULT_TAG_NAME.to_string(),
+            tag_type: TagType::default(),
+            tag_message: DEFAULT_TAG_MESSAGE.to_string(),
+            commit_message: DEFAULT_COMMIT_MESSAGE.to_string(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn from_toml(table: &Table) -> Self {
+        let mut settings = Self::default();
+        let text = |key: &str| table.get(key).and_then(|v| v.as_str()).map(String::from);
+        match text("tag_name") {
+            Some(name) if !name.contains("{version}") => eprintln!(
+                "Ignoring release tag_name '{}', it needs {{version}} to tell releases apart",
+                name
+            ),
+            Some(name) => settings.tag_name = name,
+            None => {}
+        }
+        match text("tag_type").as_deref() {
+            Some("annotated") | None => {}
+            Some("lightweight") => settings.tag_type = TagType::Lightweight,
+            Some("none") => settings.tag_type = TagType::None,
+            Some(other) => eprintln!(
+                "Ignoring release tag_type '{}', expected \"annotated\", \"lightweight\" or \"none\"",
+                other
+            ),
+        }
+        if let Some(message) = text("tag_message") {
+            settings.tag_message = message;
+        }
+        match text("commit_message") {
+            Some(message) if message.trim().is_empty() => {
+                eprintln!("Ignoring an empty release commit_message")
+            }
+            Some(message) => settings.commit_message = message,
+            None => {}
+        }
+        settings
+    }
+
+    pub fn tag_name(&self, previous: &SemVer, version: &SemVer) -> String {
+        render(&self.tag_name, previous, version)
+    }
+
+    pub fn tag_message(&self, previous: &SemVer, version: &SemVer) -> String {
+        render(&self.tag_message, previous, version)
+    }
+
+    pub fn commit_message(&self, previous: &SemVer, version: &SemVer) -> String {
+        render(&self.commit_message, previous, version)
+    }
+}
+
+fn render(template: &str, previous: &SemVer, version: &SemVer) -> String {
+    template
+        .replace("{version}", &version.to_string())
+        .replace("{previous_version}", &previous.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_templates() {
+        let settings = Settings::default();
+        let (previous, version) = (SemVer::version_0_1_0(), SemVer::new(0, 2, 0, None, None));
+        assert_eq!(settings.tag_name(&previous, &version), "v0.2.0");
+        assert_eq!(settings.tag_message(&previous, &version), "Release 0.2.0");
+        assert_eq!(
+            settings.commit_message(&previous, &version),
+            "chore: bump version 0.1.0 -> 0.2.0"
+        );
+    }
+
+    #[test]
+    fn test_release_settings() {
+        let table: Table = r#"
+            tag_name = "release-{version}"
+            tag_type = "lightweight"
+            commit_message = "chore(release): {version}"
+        "#
+        .parse()
+        .unwrap();
+        let settings = Settings::from_toml(&table);
+        let (previous, version) = (SemVer::version_0_1_0(), SemVer::new(1, 0, 0, None, None));
+        assert_eq!(settings.tag_name(&previous, &version), "release-1.0.0");
+        assert_eq!(settings.tag_type, TagType::Lightweight);
+        assert_eq!(
+            settings.commit_message(&previous, &version),
+            "chore(release): 1.0.0"
+        );
+
+        let table: Table = "tag_name = \"latest\"\ntag_type = \"none\""
+            .parse()
+            .unwrap();
+        let settings = Settings::from_toml(&table);
+        assert_eq!(settings.tag_name, DEFAULT_TAG_NAME);
+        assert_eq!(settings.tag_type, TagType::None);
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{Config, Project, SemVer};
+
+static VERSION_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?mi)^(?P<tag>Version:\s*)(?P<value>\S+)[ \t]*$").unwrap());
+
+static RELEASE_TAG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?mi)^(?P<tag>Release:\s*)(?P<number>\d+)(?P<suffix>\S*)[ \t]*$").unwrap()
+});
+
+pub struct SpecProject {
+    version: SemVer,
+    path: PathBuf,
+    file_name: PathBuf,
+}
+
+impl SpecProject {
+    fn parse_spec(spec_str: &str) -> anyhow::Result<SemVer> {
+        let caps = VERSION_TAG
+            .captures(spec_str)
+            .ok_or_else(|| anyhow!("missing Version: tag in spec file"))?;
+        SemVer::parse(&caps["value"])
+    }
+
+    /// Find the spec file in a directory, if there is exactly one.
+    pub fn find_spec_file(path: &Path) -> Option<PathBuf> {
+        let mut specs: Vec<PathBuf> = fs::read_dir(path)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "spec"))
+            .collect();
+        match specs.len() {
+            1 => specs.pop(),
+            _ => None,
+        }
+    }
+}
+
+impl Project for SpecProject {
+    fn from_dir(path: &Path) -> anyhow::Result<Self> {
+        let spec_path = Self::find_spec_file(path)
+            .ok_or_else(|| anyhow!("expected exactly one .spec file in {}", path.display()))?;
+        let raw_file = fs::read_to_string(&spec_path)?;
+        let version = Self::parse_spec(&raw_file)?;
+        let file_name = PathBuf::from(spec_path.file_name().expect("spec file has a name"));
+        Ok(Self {
+            version,
+            path: spec_path,
+            file_name,
+        })
+    }
+
+    fn get_dir(&self) -> &Path {
+        self.path.parent().expect("Project must be in a directory")
+    }
+
+    fn get_version(&self) -> SemVer {
+        self.version.clone()
+    }
+
+    fn set_version(&mut self, version: SemVer) {
+        self.version = version;
+    }
+
+    fn update_project_file(&self) -> anyhow::Result<()> {
+        let content = fs::read_to_string(&self.path)?;
+        fs::write(&self.path, set_spec_version(&content, &self.version))?;
+        Ok(())
+    }
+
+    fn get_version_file(&self) -> &Path {
+        &self.file_name
+    }
+
+    fn set_initial_release(&mut self) -> anyhow::Result<()> {
+        if SemVer::version_1_0_0() <= self.get_version() {
+            return Err(anyhow!("This repo already has an initial release"));
+        }
+        self.version = SemVer::version_1_0_0();
+        Ok(())
+    }
+
+    fn parse_version_file(&self, unparsed_str: &str) -> anyhow::Result<SemVer> {
+        Self::parse_spec(unparsed_str)
+    }
+
+    fn get_extra_files(&self, _config: &Config) -> anyhow::Result<Vec<PathBuf>> {
+        Ok(vec![])
+    }
+
+    fn get_spec_file(&self) -> Option<&Path> {
+        Some(&self.file_name)
+    }
+}
+
+/// Set the `Version:` tag. A new version starts again at `Release: 1`.
+pub fn set_spec_version(spec: &str, version: &SemVer) -> String {
+    let current = VERSION_TAG.captures(spec).map(|c| c["value"].to_string());
+    if current.as_deref() == Some(version.to_string().as_str()) {
+        return spec.to_string();
+    }
+    let spec = VERSION_TAG.replace(spec, |caps: &regex::Captures| {
+        format!("{}{}", &caps["tag"], version)
+    });
+    RELEASE_TAG
+        .replace(&spec, |caps: &regex::Captures| {
+            format!("{}1{}", &caps["tag"], &caps["suffix"])
+        })
+        .into_owned()
+}
+
+/// Release number of the package, without any `%{?dist}` style suffix.
+fn spec_release(spec: &str) -> String {
+    RELEASE_TAG
+        .captures(spec)
+        .map(|c| c["number"].to_string())
+        .unwrap_or_else(|| "1".to_string())
+}
+
+/// Add a `%changelog` stanza for a release to the top of the changelog
+/// section, creating the section if the spec file does not have one.
+pub fn prepend_changelog_stanza(
+    spec: &str,
+    version: &SemVer,
+    date: &str,
+    packager: &str,
+    entries: &[String],
+) -> String {
+    let mut stanza = format!(
+        "* {} {} - {}-{}\n",
+        date,
+        packager,
+        version,
+        spec_release(spec)
+    );
+    for entry in entries {
+        stanza.push_str(&format!("- {}\n", entry));
+    }
+
+    let mut out = String::new();
+    let mut inserted = false;
+    for line in spec.split_inclusive('\n') {
+        out.push_str(line);
+        if !inserted && line.trim_end() == "%changelog" {
+            if !line.ends_with('\n') {
+                out.push('\n');
+            }
+            out.push_str(&stanza);
+            out.push('\n');
+            inserted = true;
+        }
+    }
+
+    if !inserted {
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str("\n%changelog\n");
+        out.push_str(&stanza);
+    }
+    out
+}
+
+/// Update the version and changelog of a spec file for a release.
+pub fn write_spec_release(
+    path: &Path,
+    version: &SemVer,
+    date: &str,
+    packager: &str,
+    entries: &[String],
+) -> anyhow::Result<()> {
+    let content = fs::read_to_string(path)?;
+    let content = set_spec_version(&content, version);
+    let content = prepend_changelog_stanza(&content, version, date, packager, entries);
+    fs::write(path, content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = "Name:           example
+Version:        1.1.0
+Release:        3%{?dist}
+Summary:        An example package
+
+%description
+An example package.
+
+%changelog
+* Mon Oct 13 2026 Jane Doe <jane@example.com> - 1.1.0-3
+- Rebuild
+";
+
+    #[test]
+    fn parse_spec() {
+        let v = SpecProject::parse_spec(SPEC).unwrap();
+        assert_eq!(v, SemVer::parse("1.1.0").unwrap());
+    }
+
+    #[test]
+    fn parse_missing_version_fails() {
+        assert!(SpecProject::parse_spec("Name: example\n").is_err());
+    }
+
+    #[test]
+    fn update_version_resets_release() {
+        let updated = set_spec_version(SPEC, &SemVer::parse("1.2.0").unwrap());
+        assert!(updated.contains("Version:        1.2.0\n"));
+        assert!(updated.contains("Release:        1%{?dist}\n"));
+        assert_eq!(
+            SpecProject::parse_spec(&updated).unwrap(),
+            SemVer::parse("1.2.0").unwrap()
+        );
+    }
+
+    #[test]
+    fn update_same_version_keeps_release() {
+        let updated = set_spec_version(SPEC, &SemVer::parse("1.1.0").unwrap());
+        assert_eq!(updated, SPEC);
+    }
+
+    #[test]
+    fn prepend_stanza() {
+        let spec = set_spec_version(SPEC, &SemVer::parse("1.2.0").unwrap());
+        let updated = prepend_changelog_stanza(
+            &spec,
+            &SemVer::parse("1.2.0").unwrap(),
+            "Fri Oct 17 2026",
+            "Name <email@example.com>",
+            &["feat: a thing".to_string(), "fix: another".to_string()],
+        );
+        assert!(updated.ends_with(
+            "%changelog\n\
+             * Fri Oct 17 2026 Name <email@example.com> - 1.2.0-1\n\
+             - feat: a thing\n\
+             - fix: another\n\
+             \n\
+             * Mon Oct 13 2026 Jane Doe <jane@example.com> - 1.1.0-3\n\
+             - Rebuild\n"
+        ));
+    }
+
+    #[test]
+    fn prepend_stanza_creates_section() {
+        let updated = prepend_changelog_stanza(
+            "Name: example\nVersion: 0.1.0",
+            &SemVer::parse("0.2.0").unwrap(),
+            "Fri Oct 17 2026",
+            "Name <email@example.com>",
+            &["feat: a thing".to_string()],
+        );
+        assert_eq!(
+            updated,
+            "Name: example\nVersion: 0.1.0\n\n%changelog\n\
+             * Fri Oct 17 2026 Name <email@example.com> - 0.2.0-1\n\
+             - feat: a thing\n"
+        );
+    }
+}
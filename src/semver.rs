@@ -18,6 +18,17 @@ pub enum SemVerBump {
     Major,
 }
 
+impl Display for SemVerBump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SemVerBump::None => write!(f, "none"),
+            SemVerBump::Patch => write!(f, "patch"),
+            SemVerBump::Minor => write!(f, "minor"),
+            SemVerBump::Major => write!(f, "major"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Eq)]
 pub struct SemVer {
     major: usize,
@@ -105,6 +116,19 @@ impl SemVer {
             SemVerBump::None => self.clone(),
         }
     }
+
+    /// The most significant part of the version which changed since `previous`
+    pub fn bump_from(&self, previous: &SemVer) -> SemVerBump {
+        if self <= previous {
+            SemVerBump::None
+        } else if self.major != previous.major {
+            SemVerBump::Major
+        } else if self.minor != previous.minor {
+            SemVerBump::Minor
+        } else {
+            SemVerBump::Patch
+        }
+    }
 }
 
 impl Display for SemVer {
@@ -319,6 +343,16 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_bump_from() {
+        let v = |s| SemVer::parse(s).unwrap();
+        assert_eq!(v("2.0.0").bump_from(&v("1.4.2")), SemVerBump::Major);
+        assert_eq!(v("0.3.0").bump_from(&v("0.2.5")), SemVerBump::Minor);
+        assert_eq!(v("1.4.3").bump_from(&v("1.4.2")), SemVerBump::Patch);
+        assert_eq!(v("1.0.0").bump_from(&v("1.0.0-rc.1")), SemVerBump::Patch);
+        assert_eq!(v("1.0.0").bump_from(&v("1.0.0")), SemVerBump::None);
+    }
+
     #[test]
     fn test_bump_order() {
         assert!(SemVerBump::Major > SemVerBump::Minor);
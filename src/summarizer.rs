@@ -0,0 +1,833 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    ops::Range,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+
+use git2::{Oid, Repository};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use toml::Table;
+
+use crate::{fragment::parse_bump, git::FileDiff, semver::SemVerBump};
+
+/// The prompt used unless `summarizer_prompt` names a template file
+pub const DEFAULT_PROMPT: &str =
+    "Generate a concise changelog entry list for the following changes.
+Output one entry per line, no bullet points or numbering.
+Only include user-facing changes worth noting in a changelog.
+
+## Commits
+{commits}
+
+## Diff
+{diff}";
+
+/// The instructions sent with the JSON protocol unless `summarizer_prompt`
+/// names a template file
+pub const DEFAULT_JSON_PROMPT: &str = "Write changelog entries for the commits and diff in this JSON document.
+Only include user-facing changes worth noting in a changelog.
+Reply with JSON only, in the form:
+{\"entries\": [{\"text\": \"...\", \"category\": \"Added\", \"breaking\": false}], \"bump\": \"minor\"}
+where category is optional and bump is the release the changes need: none, patch, minor or major.";
+
+/// How many bytes of diff go in a prompt, unless `summarizer_diff_budget` says
+/// otherwise
+pub const DEFAULT_DIFF_BUDGET: usize = 100_000;
+
+/// Files left out of the diff as well as those in `summarizer_exclude`
+pub const DEFAULT_EXCLUDE: &[&str] = &["*.lock", "package-lock.json"];
+
+/// How long a request to an HTTP summarizer may take, unless `[summarizer]`
+/// sets a `timeout`. Commands have no limit by default.
+const DEFAULT_HTTP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Where cached summaries are kept, in the git directory
+const CACHE_DIR: &str = "clog/summaries";
+
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{(?P<name>[a-z_]+)\}").unwrap());
+
+/// What a prompt template can refer to, as `{version}`, `{commits}` and so on
+#[derive(Debug, Clone, Default)]
+pub struct PromptContext {
+    pub version: String,
+    pub previous_version: String,
+    /// Commit messages, newest first
+    pub commits: Vec<String>,
+    pub diff: String,
+    pub diff_stat: String,
+    /// The newest sections of the existing changelog, to copy the style of
+    pub changelog_samples: String,
+}
+
+/// Fill in the placeholders of a prompt template. Unknown placeholders are
+/// left as they are, so templates can contain other braces.
+pub fn render_prompt(template: &str, context: &PromptContext) -> String {
+    PLACEHOLDER
+        .replace_all(template, |caps: &Captures| match &caps["name"] {
+            "version" => context.version.clone(),
+            "previous_version" => context.previous_version.clone(),
+            "commits" => context.commits.join("\n"),
+            "diff" => context.diff.clone(),
+            "diff_stat" => context.diff_stat.clone(),
+            "changelog_samples" => context.changelog_samples.clone(),
+            _ => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+/// The diff of each file while it fits in the budget, and after that only
+/// how many lines changed
+pub fn budget_diff(files: &[FileDiff], budget: usize) -> String {
+    let mut remaining = budget;
+    let mut diff = String::new();
+    for file in files {
+        if file.patch.len() <= remaining {
+            remaining -= file.patch.len();
+            diff.push_str(&file.patch);
+        } else {
+            diff.push_str(&format!(
+                "{} | +{} -{} (diff left out)\n",
+                file.path, file.insertions, file.deletions
+            ));
+        }
+    }
+    diff
+}
+
+/// Split commits into runs whose diffs fit in the budget together, given the
+/// size of each commit's diff. A commit over the budget is a run of its own.
+pub fn chunks(sizes: &[usize], budget: usize) -> Vec<Range<usize>> {
+    let mut chunks = vec![];
+    let (mut start, mut total) = (0, 0);
+    for (i, size) in sizes.iter().enumerate() {
+        if i > start && total + size > budget {
+            chunks.push(start..i);
+            (start, total) = (i, 0);
+        }
+        total += size;
+    }
+    if start < sizes.len() {
+        chunks.push(start..sizes.len());
+    }
+    chunks
+}
+
+/// How clog reaches the summarizer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backend {
+    /// A shell command which reads the prompt on stdin, `summarizer_command`
+    Command(String),
+    /// An OpenAI-compatible chat completions endpoint
+    Http(HttpBackend),
+}
+
+/// A server with an OpenAI-compatible `/v1/chat/completions` endpoint, such
+/// as llama.cpp, Ollama or OpenAI itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpBackend {
+    /// Everything before `/chat/completions`, such as `http://localhost:11434/v1`
+    pub base_url: String,
+    pub model: String,
+    /// The environment variable holding the API key, if the server needs one
+    pub api_key_env: Option<String>,
+}
+
+/// What is sent to the summarizer and what it sends back
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Protocol {
+    /// A prompt in, one entry per line out
+    #[default]
+    Lines,
+    /// A JSON document in, entries with categories and a suggested bump out
+    Json,
+}
+
+/// The environment variables a sandboxed summarizer command keeps
+pub const SANDBOX_ENV: &[&str] = &[
+    "PATH", "HOME", "USER", "LANG", "LC_ALL", "LC_CTYPE", "TERM", "TMPDIR",
+];
+
+/// What to do when the summarizer fails after every retry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fallback {
+    /// Stop with an error
+    #[default]
+    Fail,
+    /// List the commits as if there were no summarizer
+    Conventional,
+}
+
+/// The `[summarizer]` table in `clog.toml`
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    /// Set with `mode = "http"`
+    pub http: Option<HttpBackend>,
+    /// How long one run of the summarizer may take
+    pub timeout: Option<Duration>,
+    pub protocol: Protocol,
+    /// How many more times to run the summarizer after it fails
+    pub retries: u32,
+    pub fallback: Fallback,
+    /// Run the command in an empty directory with only some of the
+    /// environment
+    pub sandbox: bool,
+    /// Environment variables a sandboxed command keeps besides `SANDBOX_ENV`
+    pub env: Vec<String>,
+}
+
+impl Settings {
+    pub fn from_toml(table: &Table) -> Self {
+        let mut settings = Self::default();
+        let text = |key: &str| table.get(key).and_then(|v| v.as_str()).map(String::from);
+        match text("mode").as_deref() {
+            Some("http") => match (text("base_url"), text("model")) {
+                (Some(base_url), Some(model)) => {
+                    settings.http = Some(HttpBackend {
+                        base_url,
+                        model,
+                        api_key_env: text("api_key_env"),
+                    })
+                }
+                _ => eprintln!("Ignoring summarizer mode \"http\" without a base_url and model"),
+            },
+            Some("command") | None => {}
+            Some(other) => eprintln!(
+                "Ignoring summarizer mode '{}', expected \"command\" or \"http\"",
+                other
+            ),
+        }
+        if let Some(timeout) = table.get("timeout").and_then(|v| v.as_integer()) {
+            settings.timeout = Some(Duration::from_secs(timeout.max(1) as u64));
+        }
+        match text("protocol").as_deref() {
+            Some("json") => settings.protocol = Protocol::Json,
+            Some("lines") | None => {}
+            Some(other) => eprintln!(
+                "Ignoring summarizer protocol '{}', expected \"lines\" or \"json\"",
+                other
+            ),
+        }
+        if let Some(retries) = table.get("retries").and_then(|v| v.as_integer()) {
+            settings.retries = retries.clamp(0, u32::MAX as i64) as u32;
+        }
+        match text("fallback").as_deref() {
+            Some("conventional") => settings.fallback = Fallback::Conventional,
+            Some("fail") | None => {}
+            Some(other) => eprintln!(
+                "Ignoring summarizer fallback '{}', expected \"fail\" or \"conventional\"",
+                other
+            ),
+        }
+        if let Some(sandbox) = table.get("sandbox").and_then(|v| v.as_bool()) {
+            settings.sandbox = sandbox;
+        }
+        if let Some(env) = table.get("env").and_then(|v| v.as_array()) {
+            settings.env = env
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(String::from)
+                .collect();
+        }
+        settings
+    }
+}
+
+impl Backend {
+    /// What the cache tells summarizers apart by
+    fn id(&self) -> String {
+        match self {
+            Self::Command(command) => command.clone(),
+            Self::Http(http) => format!("{} {}", http.base_url, http.model),
+        }
+    }
+
+    fn run(&self, prompt: &str, settings: &Settings) -> anyhow::Result<String> {
+        match self {
+            Self::Command(command) => run(command, prompt, settings),
+            Self::Http(http) => http.run(prompt, settings.timeout.unwrap_or(DEFAULT_HTTP_TIMEOUT)),
+        }
+    }
+}
+
+impl HttpBackend {
+    fn run(&self, prompt: &str, timeout: Duration) -> anyhow::Result<String> {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        eprintln!("Running summarizer: POST {} ({})", url, self.model);
+
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(timeout))
+            .build()
+            .into();
+        let mut request = agent.post(&url);
+        if let Some(name) = &self.api_key_env {
+            let key = std::env::var(name).map_err(|_| {
+                anyhow::anyhow!("The summarizer API key variable {} is not set", name)
+            })?;
+            request = request.header("Authorization", &format!("Bearer {}", key));
+        }
+        let body = json!({
+            "model": self.model,
+            "messages": [{"role": "user", "content": prompt}],
+        });
+        let response: serde_json::Value = request
+            .send_json(&body)
+            .and_then(|mut r| r.body_mut().read_json())
+            .map_err(|e| anyhow::anyhow!("Summarizer request to {} failed: {}", url, e))?;
+
+        let content = completion_text(&response)?;
+        for line in content.lines() {
+            eprintln!("{}", line);
+        }
+        Ok(content.to_string())
+    }
+}
+
+/// The message in a chat completions response
+fn completion_text(response: &serde_json::Value) -> anyhow::Result<&str> {
+    response
+        .pointer("/choices/0/message/content")
+        .and_then(|c| c.as_str())
+        .ok_or_else(|| anyhow::anyhow!("The summarizer response has no message: {}", response))
+}
+
+/// A prompt for the summarizer
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub text: String,
+    /// The newest and oldest commit the prompt covers
+    pub commits: Option<(Oid, Oid)>,
+}
+
+/// Summaries already made, kept in `.git/clog/summaries` so that making a
+/// changelog again does not run the summarizer again
+pub struct SummaryCache {
+    dir: PathBuf,
+}
+
+impl SummaryCache {
+    pub fn new(repo: &Repository) -> Self {
+        Self {
+            dir: repo.path().join(CACHE_DIR),
+        }
+    }
+
+    /// Summaries are found by the commits they cover and a hash of the
+    /// summarizer and prompt, so a change to either makes a new summary
+    fn path(&self, (newest, oldest): (Oid, Oid), command: &str, prompt: &str) -> PathBuf {
+        let hash = fnv1a(command.bytes().chain([0]).chain(prompt.bytes()));
+        self.dir
+            .join(format!("{}-{}-{:016x}.txt", newest, oldest, hash))
+    }
+
+    pub fn get(&self, commits: (Oid, Oid), command: &str, prompt: &str) -> Option<String> {
+        fs::read_to_string(self.path(commits, command, prompt)).ok()
+    }
+
+    pub fn put(
+        &self,
+        commits: (Oid, Oid),
+        command: &str,
+        prompt: &str,
+        output: &str,
+    ) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(commits, command, prompt), output)?;
+        Ok(())
+    }
+
+    /// Delete every cached summary
+    pub fn clear(&self) -> anyhow::Result<()> {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)?;
+        }
+        Ok(())
+    }
+}
+
+/// 64-bit FNV-1a, which unlike the standard library's hasher is the same in
+/// every build of clog
+fn fnv1a(bytes: impl Iterator<Item = u8>) -> u64 {
+    bytes.fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// A commit as the JSON protocol describes it
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitInput {
+    pub hash: String,
+    pub author: String,
+    pub message: String,
+    /// Paths of the files the commit changed
+    pub files: Vec<String>,
+}
+
+/// The document sent to the summarizer with the JSON protocol
+pub fn json_input(instructions: &str, context: &PromptContext, commits: &[CommitInput]) -> String {
+    let input = json!({
+        "instructions": instructions,
+        "version": context.version,
+        "previous_version": context.previous_version,
+        "commits": commits,
+        "diff": context.diff,
+    });
+    serde_json::to_string_pretty(&input).expect("JSON values always serialise")
+}
+
+/// An entry written by the summarizer
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SummaryEntry {
+    pub text: String,
+    /// The section the entry goes in
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub breaking: bool,
+}
+
+/// What the summarizer made of some changes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub entries: Vec<SummaryEntry>,
+    /// The release the summarizer thinks the changes need
+    pub bump: Option<SemVerBump>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonOutput {
+    Entries(Vec<SummaryEntry>),
+    Summary {
+        entries: Vec<SummaryEntry>,
+        #[serde(default)]
+        bump: Option<String>,
+    },
+}
+
+/// Read the summarizer's output. With the JSON protocol, anything around the
+/// JSON, such as a Markdown code fence, is ignored.
+pub fn parse_summary(output: &str, protocol: Protocol) -> anyhow::Result<Summary> {
+    if protocol == Protocol::Lines {
+        let entries = output
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| SummaryEntry {
+                text: l.to_string(),
+                category: None,
+                breaking: false,
+            })
+            .collect();
+        return Ok(Summary {
+            entries,
+            bump: None,
+        });
+    }
+
+    let start = output.find(['{', '[']);
+    let end = output.rfind(['}', ']']);
+    let json = match (start, end) {
+        (Some(start), Some(end)) if start < end => &output[start..=end],
+        _ => anyhow::bail!("The summarizer did not reply with JSON: {}", output.trim()),
+    };
+    let parsed: JsonOutput = serde_json::from_str(json)
+        .map_err(|e| anyhow::anyhow!("Failed to read the summarizer's JSON: {}", e))?;
+    Ok(match parsed {
+        JsonOutput::Entries(entries) => Summary {
+            entries,
+            bump: None,
+        },
+        JsonOutput::Summary { entries, bump } => Summary {
+            entries,
+            bump: bump.as_deref().and_then(|b| {
+                let level = parse_bump(b);
+                if level.is_none() {
+                    eprintln!("Ignoring the summarizer's bump '{}'", b);
+                }
+                level
+            }),
+        },
+    })
+}
+
+/// Summarise a prompt, from the cache if it has it. The summarizer is run
+/// again, up to `settings.retries` times, when it fails or its output cannot
+/// be read.
+pub fn summarize(
+    backend: &Backend,
+    settings: &Settings,
+    prompt: &Prompt,
+    cache: Option<&SummaryCache>,
+) -> anyhow::Result<Summary> {
+    let id = backend.id();
+    let cache = cache.zip(prompt.commits);
+    let cached = cache.and_then(|(c, commits)| c.get(commits, &id, &prompt.text));
+    if let Some(Ok(summary)) = cached.map(|output| parse_summary(&output, settings.protocol)) {
+        return Ok(summary);
+    }
+
+    let mut attempt = 0;
+    loop {
+        let result = backend.run(&prompt.text, settings).and_then(|output| {
+            parse_summary(&output, settings.protocol).map(|summary| (output, summary))
+        });
+        match result {
+            Ok((output, summary)) => {
+                if let Some((cache, commits)) = cache {
+                    cache.put(commits, &id, &prompt.text, &output)?;
+                }
+                return Ok(summary);
+            }
+            Err(e) if attempt < settings.retries => {
+                attempt += 1;
+                eprintln!("{:#}\nRetrying ({}/{})", e, attempt, settings.retries);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Send the prompt to the summarizer command and read back what it prints,
+/// killing it if it takes longer than the timeout
+fn run(command: &str, prompt: &str, settings: &Settings) -> anyhow::Result<String> {
+    eprintln!("Running summarizer: {}", command);
+
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped());
+    // its own process group, so whatever it starts is stopped with it
+    #[cfg(unix)]
+    cmd.process_group(0);
+    // kept until the command is done with it
+    let scratch = match settings.sandbox {
+        true => Some(tempfile::tempdir()?),
+        false => None,
+    };
+    if let Some(scratch) = &scratch {
+        let allowed = SANDBOX_ENV
+            .iter()
+            .copied()
+            .chain(settings.env.iter().map(String::as_str));
+        cmd.current_dir(scratch.path())
+            .env_clear()
+            .envs(allowed.filter_map(|name| std::env::var_os(name).map(|v| (name, v))));
+    }
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to launch summarizer '{}': {}", command, e))?;
+    let timeout = settings.timeout;
+
+    // The prompt is written and the output read on their own threads, so a
+    // command which stops reading or writing cannot outlast the timeout.
+    let mut stdin = child.stdin.take().unwrap();
+    let prompt = prompt.to_string();
+    thread::spawn(move || {
+        // Ignore broken pipe — if the command exits early due to an error,
+        // we'll catch the real cause via the exit status below.
+        let _ = stdin.write_all(prompt.as_bytes());
+    });
+    let stdout = child.stdout.take().unwrap();
+    let reader = thread::spawn(move || -> std::io::Result<String> {
+        let mut output = String::new();
+        for line in BufReader::new(stdout).lines() {
+            let line = line?;
+            eprintln!("{}", line);
+            output.push_str(&line);
+            output.push('\n');
+        }
+        Ok(output)
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if let Some(timeout) = timeout.filter(|t| started.elapsed() >= *t) {
+            kill_group(&mut child);
+            anyhow::bail!(
+                "Summarizer '{}' did not finish within {} seconds",
+                command,
+                timeout.as_secs()
+            );
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    // what the command left running would keep its output open, and the
+    // reader waiting, after the command is done
+    kill_group(&mut child);
+    let output = reader
+        .join()
+        .map_err(|_| anyhow::anyhow!("Failed to read the output of summarizer '{}'", command))??;
+    if !status.success() {
+        anyhow::bail!(
+            "Summarizer '{}' exited with status {}. \
+             Check your summarizer_command in clog.toml.",
+            command,
+            status
+        );
+    }
+
+    Ok(output)
+}
+
+/// Kill a command run through `sh` along with anything it started
+fn kill_group(child: &mut Child) {
+    let killed = Command::new("kill")
+        .args(["-KILL", "--", &format!("-{}", child.id())])
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !killed {
+        let _ = child.kill();
+    }
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[test]
+    fn test_render_prompt() {
+        let context = PromptContext {
+            version: "1.2.0".to_string(),
+            previous_version: "1.1.0".to_string(),
+            commits: vec!["feat: b".to_string(), "fix: a".to_string()],
+            diff_stat: " a.rs | 2 +-".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_prompt(
+                "{previous_version} -> {version}\n{commits}\n{diff_stat}\n{unknown} {\"json\": 1}",
+                &context
+            ),
+            "1.1.0 -> 1.2.0\nfeat: b\nfix: a\n a.rs | 2 +-\n{unknown} {\"json\": 1}"
+        );
+    }
+
+    fn file(path: &str, patch_len: usize) -> FileDiff {
+        FileDiff {
+            path: path.to_string(),
+            patch: "x".repeat(patch_len),
+            insertions: 3,
+            deletions: 1,
+        }
+    }
+
+    #[test]
+    fn test_budget_diff() {
+        let files = [file("a", 4), file("b", 8), file("c", 2)];
+        assert_eq!(budget_diff(&files, 10), "xxxxb | +3 -1 (diff left out)\nxx");
+        assert_eq!(budget_diff(&files, 14), "x".repeat(14));
+    }
+
+    #[rstest]
+    #[case::fits(&[1, 2, 3], 10, vec![0..3])]
+    #[case::split(&[4, 4, 4, 1], 8, vec![0..2, 2..4])]
+    #[case::oversized(&[2, 20, 2], 8, vec![0..1, 1..2, 2..3])]
+    #[case::empty(&[], 8, vec![])]
+    fn test_chunks(
+        #[case] sizes: &[usize],
+        #[case] budget: usize,
+        #[case] expected: Vec<Range<usize>>,
+    ) {
+        assert_eq!(chunks(sizes, budget), expected);
+    }
+
+    #[test]
+    fn test_http_settings() {
+        let table: Table = r#"
+            mode = "http"
+            base_url = "http://localhost:11434/v1"
+            model = "llama3"
+            timeout = 30
+        "#
+        .parse()
+        .unwrap();
+        let settings = Settings::from_toml(&table);
+        assert_eq!(
+            settings.http,
+            Some(HttpBackend {
+                base_url: "http://localhost:11434/v1".to_string(),
+                model: "llama3".to_string(),
+                api_key_env: None,
+            })
+        );
+        assert_eq!(settings.timeout, Some(Duration::from_secs(30)));
+
+        let table: Table = "mode = \"http\"".parse().unwrap();
+        assert_eq!(Settings::from_toml(&table).http, None);
+    }
+
+    #[test]
+    fn test_retry_settings() {
+        let table: Table = "retries = 2\nfallback = \"conventional\"".parse().unwrap();
+        let settings = Settings::from_toml(&table);
+        assert_eq!(settings.retries, 2);
+        assert_eq!(settings.fallback, Fallback::Conventional);
+
+        let settings = Settings::from_toml(&Table::new());
+        assert_eq!(settings.retries, 0);
+        assert_eq!(settings.fallback, Fallback::Fail);
+    }
+
+    #[test]
+    fn test_run_times_out() {
+        let started = Instant::now();
+        let settings = Settings {
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let result = run("sleep 5", "prompt", &settings);
+        assert!(result.unwrap_err().to_string().contains("did not finish"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // what the command started is killed with it
+        let dir = tempfile::tempdir().unwrap();
+        let pid_path = dir.path().join("pid");
+        let command = format!("sleep 30 & echo $! > {}; wait", pid_path.display());
+        assert!(run(&command, "prompt", &settings).is_err());
+        let pid = fs::read_to_string(&pid_path).unwrap();
+        let stopped = || {
+            fs::read_to_string(format!("/proc/{}/stat", pid.trim()))
+                .map_or(true, |stat| stat.contains(") Z "))
+        };
+        let waited = Instant::now();
+        while !stopped() && waited.elapsed() < Duration::from_secs(2) {
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(stopped(), "sleep still runs");
+
+        let output = run("cat", "A\nB", &Settings::default()).unwrap();
+        assert_eq!(output, "A\nB\n");
+
+        // nor does it wait for what the command leaves running
+        let started = Instant::now();
+        let command = "cat > /dev/null; (sleep 5; echo late) & echo early";
+        let output = run(command, "prompt", &Settings::default()).unwrap();
+        assert_eq!(output, "early\n");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_run_sandboxed() {
+        let settings = Settings {
+            sandbox: true,
+            env: vec!["CLOG_TEST_KEPT".to_string()],
+            ..Default::default()
+        };
+        std::env::set_var("CLOG_TEST_KEPT", "kept");
+        std::env::set_var("CLOG_TEST_DROPPED", "dropped");
+        let command = "ls -A; pwd; echo \"$CLOG_TEST_KEPT/$CLOG_TEST_DROPPED\"";
+        let output = run(command, "", &settings).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", output);
+        assert_ne!(
+            Path::new(lines[0]),
+            std::env::current_dir().unwrap().as_path()
+        );
+        assert_eq!(lines[1], "kept/");
+    }
+
+    #[test]
+    fn test_completion_text() {
+        let response = json!({"choices": [{"message": {"role": "assistant", "content": "A\nB"}}]});
+        assert_eq!(completion_text(&response).unwrap(), "A\nB");
+        assert!(completion_text(&json!({"error": "overloaded"})).is_err());
+    }
+
+    fn entry(text: &str, category: Option<&str>, breaking: bool) -> SummaryEntry {
+        SummaryEntry {
+            text: text.to_string(),
+            category: category.map(String::from),
+            breaking,
+        }
+    }
+
+    #[test]
+    fn test_parse_json_summary() {
+        let output = "Here you go:\n```json\n{\"entries\": [\
+                      {\"text\": \"Add a flag\", \"category\": \"Added\"},\
+                      {\"text\": \"Drop the v1 API\", \"breaking\": true}],\
+                      \"bump\": \"major\"}\n```\n";
+        assert_eq!(
+            parse_summary(output, Protocol::Json).unwrap(),
+            Summary {
+                entries: vec![
+                    entry("Add a flag", Some("Added"), false),
+                    entry("Drop the v1 API", None, true),
+                ],
+                bump: Some(SemVerBump::Major),
+            }
+        );
+
+        let output = "[{\"text\": \"Fix a crash\", \"category\": \"Fixed\"}]";
+        assert_eq!(
+            parse_summary(output, Protocol::Json).unwrap().entries,
+            vec![entry("Fix a crash", Some("Fixed"), false)]
+        );
+        assert!(parse_summary("- Fix a crash", Protocol::Json).is_err());
+    }
+
+    #[test]
+    fn test_parse_lines_summary() {
+        assert_eq!(
+            parse_summary("A\n\nB\n", Protocol::Lines).unwrap().entries,
+            vec![entry("A", None, false), entry("B", None, false)]
+        );
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a("".bytes()), 0xcbf29ce484222325);
+        assert_eq!(fnv1a("a".bytes()), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_summary_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let cache = SummaryCache::new(&repo);
+        let commits = (
+            Oid::from_bytes(&[1; 20]).unwrap(),
+            Oid::from_bytes(&[2; 20]).unwrap(),
+        );
+        let output = "An entry\nAnother\n";
+
+        assert_eq!(cache.get(commits, "llm", "prompt"), None);
+        cache.put(commits, "llm", "prompt", output).unwrap();
+        assert_eq!(cache.get(commits, "llm", "prompt").as_deref(), Some(output));
+        assert_eq!(cache.get(commits, "llm", "other prompt"), None);
+        assert_eq!(cache.get(commits, "other llm", "prompt"), None);
+
+        cache.clear().unwrap();
+        assert_eq!(cache.get(commits, "llm", "prompt"), None);
+    }
+
+    #[test]
+    fn test_default_prompt() {
+        let context = PromptContext {
+            commits: vec!["feat: b".to_string()],
+            diff: "+b".to_string(),
+            ..Default::default()
+        };
+        let prompt = render_prompt(DEFAULT_PROMPT, &context);
+        assert!(prompt.ends_with("## Commits\nfeat: b\n\n## Diff\n+b"));
+    }
+}
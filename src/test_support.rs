@@ -10,7 +10,7 @@ use crate::{
     git::repo_has_commits,
     python::PyProject,
     semver::{SemVer, SemVerBump},
-    HistoryItem, HistoryItemKind, Project,
+    HistoryItem, HistoryItemKind, Person, Project,
 };
 
 static CLOG_MSG: Lazy<Regex> = Lazy::new(|| {
@@ -154,6 +154,8 @@ pub fn empty_commit(repo: &Repository, message: &str) -> anyhow::Result<Oid> {
         // the inedx is basically the staging area. It is a file
         // that stores what will go into the next commit gitbook, p97
         let mut index = repo.index()?;
+        // clog may have changed the index on disk since it was last read
+        index.read(true)?;
 
         // add current state of index to git object db as a tree
         index.write_tree()?
@@ -189,6 +191,22 @@ pub fn empty_commit(repo: &Repository, message: &str) -> anyhow::Result<Oid> {
     Ok(commit_id)
 }
 
+/// Write a file in the working directory and commit it on the current branch
+pub fn commit_file(
+    repo: &Repository,
+    path: &str,
+    contents: &str,
+    message: &str,
+) -> anyhow::Result<Oid> {
+    let workdir = repo.workdir().expect("test repos are not bare");
+    fs::write(workdir.join(path), contents)?;
+    let mut index = repo.index()?;
+    index.read(true)?;
+    index.add_path(Path::new(path))?;
+    index.write()?;
+    empty_commit(repo, message)
+}
+
 /// Creates a new branch from `HEAD`, checks it out, and runs a user-provided closure
 /// to perform commits or changes on that branch.
 fn make_branch<'a, F>(repo: &'a Repository, name: &'a str, f: F) -> anyhow::Result<Commit<'a>>
@@ -302,6 +320,7 @@ pub struct TestCommitWrapper {
     message: String,
     version: SemVer,
     kind: HistoryItemKind,
+    authors: Vec<Person>,
 }
 
 impl TestCommitWrapper {
@@ -310,12 +329,21 @@ impl TestCommitWrapper {
             message: message.to_string(),
             version,
             kind,
+            authors: vec![],
         }
     }
 
     pub fn new_normal(message: &str, version: SemVer) -> Self {
         Self::new(message, version, HistoryItemKind::Normal)
     }
+
+    pub fn with_author(mut self, name: &str, email: &str) -> Self {
+        self.authors.push(Person {
+            name: name.to_string(),
+            email: email.to_string(),
+        });
+        self
+    }
 }
 
 impl HistoryItem for TestCommitWrapper {
@@ -330,4 +358,8 @@ impl HistoryItem for TestCommitWrapper {
     fn kind(&self) -> crate::HistoryItemKind {
         self.kind
     }
+
+    fn authors(&self) -> Vec<Person> {
+        self.authors.clone()
+    }
 }
@@ -278,3 +278,1247 @@ fn test_forbidden_redo(stable_repo_dir: TempDir) {
     run_clog_redo_fail(&stable_repo_dir);
     assert_eq!(v1, get_python_pyroject_version(&stable_repo_dir).unwrap());
 }
+
+#[rstest]
+fn spec_file_changelog(#[values(false, true)] rpm_spec_names_the_project: bool) {
+    let dir = TempDir::new().unwrap();
+    let repo = Repository::init(&dir).unwrap();
+    let spec = "Name:           example\nVersion:        1.1.0\nRelease:        3%{?dist}\n\
+                Summary:        An example package\n\n%description\nAn example package.\n\n\
+                %changelog\n";
+    commit_file(&repo, "example.spec", spec, "Initial commit").unwrap();
+    if rpm_spec_names_the_project {
+        commit_file(
+            &repo,
+            "clog.toml",
+            "rpm_spec = \"example.spec\"\n",
+            "chore: configure clog",
+        )
+        .unwrap();
+    }
+    empty_commit(&repo, "feat: a new option").unwrap();
+
+    run_clog(&dir);
+
+    let spec = fs::read_to_string(dir.join("example.spec")).unwrap();
+    assert!(spec.contains("Version:        1.2.0\nRelease:        1%{?dist}\n"));
+    assert_eq!(spec.matches(" - 1.2.0-1\n").count(), 1, "{}", spec);
+    assert_eq!(
+        spec.matches("- feat: a new option\n").count(),
+        1,
+        "{}",
+        spec
+    );
+    assert_clog_commit_version(&dir, SemVer::new(1, 2, 0, None, None));
+    assert_repo_is_clean(&repo);
+}
+
+#[rstest]
+fn release_notes_json(pre_stable_branches_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_branches_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "release_notes_json = \"releases.json\"\n",
+        "chore: configure clog",
+    )
+    .unwrap();
+    run_clog(&pre_stable_branches_repo_dir);
+    empty_commit(&repo, "feat(parser)!: test commit").unwrap();
+    run_clog(&pre_stable_branches_repo_dir);
+    assert_repo_is_clean(&repo);
+
+    let json = fs::read_to_string(pre_stable_branches_repo_dir.join("releases.json")).unwrap();
+    let records: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let records = records.as_array().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["version"], "0.3.0");
+    assert_eq!(records[0]["previous_version"], "0.2.0");
+    assert_eq!(records[0]["bump"], "minor");
+    assert_eq!(records[0]["entries"][0]["type"], "feat");
+    assert_eq!(records[0]["entries"][0]["scope"], "parser");
+    assert_eq!(records[0]["entries"][0]["breaking"], true);
+    assert_eq!(records[1]["version"], "0.2.0");
+    assert_eq!(records[1]["entries"].as_array().unwrap().len(), 4);
+}
+
+#[rstest]
+fn append_changelog_replaces_existing_version(pre_stable_branches_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_branches_repo_dir).unwrap();
+    // a release commit which failed after writing the changelog
+    commit_file(
+        &repo,
+        "Changelog.md",
+        "# Version 0.2.0\n- fix: bug in B\n# Version 0.1.0\n- Initial Commit\n",
+        "docs: changelog",
+    )
+    .unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .arg("--yes")
+        .current_dir(&pre_stable_branches_repo_dir)
+        .assert()
+        .success();
+    let changelog = fs::read_to_string(pre_stable_branches_repo_dir.join("Changelog.md")).unwrap();
+    assert_eq!(changelog,"# Version 0.2.0\n- fix: bug in B\n- feat: add feature B\n- fix!: bug in A\n- feat: add feature A\n# Version 0.1.0\n- Initial Commit\n");
+}
+
+#[rstest]
+fn reformat_changelog(pre_stable_branches_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_branches_repo_dir).unwrap();
+    run_clog(&pre_stable_branches_repo_dir);
+    commit_file(
+        &repo,
+        "clog.toml",
+        "changelog_format = \"keep-a-changelog\"\n",
+        "chore: configure clog",
+    )
+    .unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .arg("changelog")
+        .arg("reformat")
+        .current_dir(&pre_stable_branches_repo_dir)
+        .assert()
+        .success()
+        .stderr("");
+    let changelog = fs::read_to_string(pre_stable_branches_repo_dir.join("Changelog.md")).unwrap();
+    let (_, releases) = changelog.split_once("## [").unwrap();
+    assert!(releases.starts_with("0.2.0] - "));
+    assert!(releases.contains("\n- fix: bug in B\n- feat: add feature B\n- fix!: bug in A\n- feat: add feature A\n\n## [0.1.0]\n- Initial Commit\n"));
+}
+
+#[rstest]
+fn reformat_changelog_warnings(pre_stable_branches_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_branches_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "changelog_format = \"keep-a-changlog\"\n",
+        "chore: configure clog",
+    )
+    .unwrap();
+    let reformat = || {
+        let output = cargo_bin_cmd!(pkg_name!())
+            .args(["changelog", "reformat"])
+            .current_dir(&pre_stable_branches_repo_dir)
+            .assert()
+            .success()
+            .get_output()
+            .clone();
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+    commit_file(
+        &repo,
+        "Changelog.md",
+        "# Changelog\n\n## [0.2.0] - 2026-10-01\n- fix: bug\n\n## [0.1.0]\n- Initial Commit\n",
+        "docs: a changelog",
+    )
+    .unwrap();
+
+    let stderr = reformat();
+    assert!(stderr.contains("Unknown changelog_format 'keep-a-changlog'"));
+    assert!(stderr.contains("no release dates"));
+    let changelog = fs::read_to_string(pre_stable_branches_repo_dir.join("Changelog.md")).unwrap();
+    assert!(changelog.starts_with("# Version 0.2.0\n- fix: bug\n"));
+}
+
+#[rstest]
+fn edit_changelog(pre_stable_branches_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_branches_repo_dir).unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .arg("bump")
+        .arg("--yes")
+        .arg("--edit")
+        .env(
+            "GIT_EDITOR",
+            "sed -i -e 's/^- feat: add feature A$/- Feature A/' -e '/bug in A/d'",
+        )
+        .current_dir(&pre_stable_branches_repo_dir)
+        .assert()
+        .success();
+    assert_repo_is_clean(&repo);
+    assert_clog_commit_version(
+        &pre_stable_branches_repo_dir,
+        SemVer::parse("0.2.0").unwrap(),
+    );
+    let changelog = fs::read_to_string(pre_stable_branches_repo_dir.join("Changelog.md")).unwrap();
+    assert_eq!(changelog,"# Version 0.2.0\n- fix: bug in B\n- feat: add feature B\n- Feature A\n# Version 0.1.0\n- Initial Commit\n");
+}
+
+#[rstest]
+fn edit_changelog_empty_aborts(pre_stable_branches_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_branches_repo_dir).unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .arg("--yes")
+        .arg("--edit")
+        .env("GIT_EDITOR", ": >")
+        .current_dir(&pre_stable_branches_repo_dir)
+        .assert()
+        .failure();
+    assert_repo_is_clean(&repo);
+    let v = get_python_pyroject_version(&pre_stable_branches_repo_dir).unwrap();
+    assert_eq!(v, SemVer::new(0, 1, 0, None, None));
+}
+
+#[rstest]
+fn edit_changelog_heading_changed(pre_stable_branches_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_branches_repo_dir).unwrap();
+    let output = cargo_bin_cmd!(pkg_name!())
+        .arg("--yes")
+        .arg("--edit")
+        .env(
+            "GIT_EDITOR",
+            "sed -i -e 's/^# Version 0.2.0$/# Version 0.2.0 (draft)/'",
+        )
+        .current_dir(&pre_stable_branches_repo_dir)
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("The heading for version 0.2.0 is missing from the edited changelog"));
+    assert_repo_is_clean(&repo);
+}
+
+#[rstest]
+fn edit_only_applies_to_releases(pre_stable_repo_dir: TempDir) {
+    for args in [
+        &["notes", "--edit"][..],
+        &["--edit", "notes"],
+        &["fragment", "--edit", "add", "x"],
+    ] {
+        cargo_bin_cmd!(pkg_name!())
+            .args(args)
+            .current_dir(&pre_stable_repo_dir)
+            .assert()
+            .failure();
+    }
+    assert!(!pre_stable_repo_dir.join(".changes").exists());
+}
+
+#[rstest]
+fn changelog_links(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "[links]\nrepository = \"https://github.com/o/r\"\n",
+        "chore: configure clog",
+    )
+    .unwrap();
+    let oid = empty_commit(&repo, "feat: add thing (#12)").unwrap();
+    run_clog(&pre_stable_repo_dir);
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    let short = &oid.to_string()[..7];
+    assert_eq!(
+        changelog,
+        format!(
+            "# Version 0.2.0\n- feat: add thing ([#12](https://github.com/o/r/pull/12)) \
+             ([{short}](https://github.com/o/r/commit/{oid}))\n# Version 0.1.0\n- Initial Commit\n"
+        )
+    );
+}
+
+#[rstest]
+fn changelog_contributors(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "contributors = true\n",
+        "chore: configure clog",
+    )
+    .unwrap();
+    commit_file(
+        &repo,
+        ".mailmap",
+        "Alice Example <alice@example.com> <alice@old.example.com>\n",
+        "chore: add mailmap",
+    )
+    .unwrap();
+    empty_commit(
+        &repo,
+        "feat: pair on a thing\n\nCo-authored-by: alice <alice@old.example.com>",
+    )
+    .unwrap();
+    run_clog(&pre_stable_repo_dir);
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert_eq!(
+        changelog,
+        "# Version 0.2.0\n- feat: pair on a thing\n## Contributors\n\
+         - Test User (first contribution)\n\
+         - Alice Example (first contribution)\n# Version 0.1.0\n- Initial Commit\n"
+    );
+}
+
+#[rstest]
+fn breaking_changes_block(pre_stable_branches_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_branches_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "breaking_changes = true\n",
+        "chore: configure clog",
+    )
+    .unwrap();
+    empty_commit(
+        &repo,
+        "feat: new config\n\nBREAKING CHANGE: the `path` key is now `dir`",
+    )
+    .unwrap();
+    run_clog(&pre_stable_branches_repo_dir);
+    let changelog = fs::read_to_string(pre_stable_branches_repo_dir.join("Changelog.md")).unwrap();
+    assert_eq!(changelog,"# Version 0.2.0\n## Breaking changes\n- the `path` key is now `dir`\n- bug in A\n## Changes\n- fix: bug in B\n- feat: add feature B\n- feat: add feature A\n# Version 0.1.0\n- Initial Commit\n");
+}
+
+#[rstest]
+fn breaking_footer_is_listed(stable_repo_dir: TempDir) {
+    let repo = Repository::open(&stable_repo_dir).unwrap();
+    empty_commit(
+        &repo,
+        "refactor: drop the v1 api\n\nBREAKING CHANGE: the v1 api is gone",
+    )
+    .unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .arg("notes")
+        .current_dir(&stable_repo_dir)
+        .assert()
+        .success()
+        .stdout("# Version 2.0.0\n- refactor: drop the v1 api\n");
+}
+
+#[rstest]
+fn notes_for_unreleased_changes(pre_stable_branches_repo_dir: TempDir) {
+    // notes can be made with uncommitted changes and write nothing
+    fs::write(pre_stable_branches_repo_dir.join("scratch.txt"), "wip").unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .arg("notes")
+        .current_dir(&pre_stable_branches_repo_dir)
+        .assert()
+        .success()
+        .stdout(
+            "# Version 0.2.0\n- fix: bug in B\n\
+             - feat: add feature B\n- fix!: bug in A\n- feat: add feature A\n",
+        );
+    assert!(!pre_stable_branches_repo_dir.join("Changelog.md").exists());
+}
+
+#[rstest]
+fn notes_for_version_and_range(pre_stable_branches_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_branches_repo_dir).unwrap();
+    run_clog(&pre_stable_branches_repo_dir);
+    empty_commit(&repo, "feat: later feature").unwrap();
+
+    cargo_bin_cmd!(pkg_name!())
+        .args(["notes", "v0.2.0", "--output", "notes.md"])
+        .current_dir(&pre_stable_branches_repo_dir)
+        .assert()
+        .success()
+        .stdout("");
+    let notes = fs::read_to_string(pre_stable_branches_repo_dir.join("notes.md")).unwrap();
+    assert_eq!(
+        notes,
+        "# Version 0.2.0\n- fix: bug in B\n\
+         - feat: add feature B\n- fix!: bug in A\n- feat: add feature A\n"
+    );
+
+    cargo_bin_cmd!(pkg_name!())
+        .args(["notes", "--from", "v0.2.0", "--to", "HEAD"])
+        .current_dir(&pre_stable_branches_repo_dir)
+        .assert()
+        .success()
+        .stdout("# Version 0.3.0\n- feat: later feature\n");
+}
+
+#[rstest]
+fn include_rules_list_without_bumping(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "[[include]]\npattern = \"^perf:\"\nsection = \"Performance\"\n",
+        "chore: configure clog",
+    )
+    .unwrap();
+    empty_commit(&repo, "perf: faster startup").unwrap();
+    run_clog(&pre_stable_repo_dir);
+    assert!(!pre_stable_repo_dir.join("Changelog.md").exists());
+
+    empty_commit(&repo, "fix: a crash").unwrap();
+    run_clog(&pre_stable_repo_dir);
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert_eq!(
+        changelog,
+        "# Version 0.1.1\n- fix: a crash\n## Performance\n- perf: faster startup\n\
+         # Version 0.1.0\n- Initial Commit\n"
+    );
+}
+
+#[rstest]
+fn gitmoji_convention_with_custom_rules(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "convention = \"gitmoji\"\n\n[patterns]\npatch = [\"^:arrow_up:\"]\n\n\
+         [[include]]\npattern = \"^:arrow_up:\"\nsection = \"Dependencies\"\n",
+        "chore: configure clog",
+    )
+    .unwrap();
+    empty_commit(&repo, ":sparkles: add a thing").unwrap();
+    empty_commit(&repo, "🐛 fix a crash").unwrap();
+    empty_commit(&repo, ":arrow_up: bump serde").unwrap();
+    run_clog(&pre_stable_repo_dir);
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert_eq!(
+        changelog,
+        "# Version 0.2.0\n## Dependencies\n- :arrow_up: bump serde\n## Added\n\
+         - :sparkles: add a thing\n## Fixed\n- 🐛 fix a crash\n# Version 0.1.0\n- Initial Commit\n"
+    );
+}
+
+#[rstest]
+fn breaking_dependency_updates(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "[dependencies]\n",
+        "chore: configure clog",
+    )
+    .unwrap();
+    empty_commit(&repo, "chore(deps): bump toml from 0.9.7 to 0.9.8").unwrap();
+    empty_commit(&repo, "chore(deps)!: drop serde 0.x").unwrap();
+    run_clog(&pre_stable_repo_dir);
+    assert_clog_commit_version(&pre_stable_repo_dir, SemVer::new(0, 2, 0, None, None));
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert_eq!(
+        changelog,
+        "# Version 0.2.0\n- chore(deps)!: drop serde 0.x\n## Dependencies\n- toml 0.9.7 → 0.9.8\n\
+         # Version 0.1.0\n- Initial Commit\n"
+    );
+}
+
+#[rstest]
+fn lockfile_dependency_updates(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    let lock = |serde: &str| {
+        format!(
+            "version = 4\n\n[[package]]\nname = \"serde\"\nversion = \"{serde}\"\n\n\
+             [[package]]\nname = \"toml\"\nversion = \"0.9.8\"\n"
+        )
+    };
+    commit_file(
+        &repo,
+        "clog.toml",
+        "lockfile_changes = true\n",
+        "chore: configure clog",
+    )
+    .unwrap();
+    commit_file(&repo, "Cargo.lock", &lock("1.0.1"), "feat: add a lockfile").unwrap();
+    run_clog(&pre_stable_repo_dir);
+
+    commit_file(&repo, "Cargo.lock", &lock("1.0.2"), "fix: update serde").unwrap();
+    run_clog(&pre_stable_repo_dir);
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert_eq!(
+        changelog,
+        "# Version 0.2.1\n- fix: update serde\n## Dependency updates\n- serde 1.0.1 → 1.0.2\n\
+         # Version 0.2.0\n- feat: add a lockfile\n## Dependency updates\n- serde 1.0.1 (added)\n\
+         - toml 0.9.8 (added)\n# Version 0.1.0\n- Initial Commit\n"
+    );
+}
+
+#[rstest]
+fn fragments_only_in_unreleased_notes(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    empty_commit(&repo, "fix: a crash").unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .args(["fragment", "add", "--bump", "minor", "A new flag"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success();
+
+    cargo_bin_cmd!(pkg_name!())
+        .arg("notes")
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success()
+        .stdout("# Version 0.2.0\n- A new flag\n- fix: a crash\n");
+    cargo_bin_cmd!(pkg_name!())
+        .args(["notes", "--from", "HEAD~1", "--to", "HEAD"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success()
+        .stdout("# Version 0.1.1\n- fix: a crash\n");
+}
+
+#[rstest]
+fn fragments_bump_and_are_released(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .args(["fragment", "add", "--bump", "minor", "--category", "Added"])
+        .arg("A `--flag` for the thing")
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success();
+    let fragment = fs::read_dir(pre_stable_repo_dir.join(".changes"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let name = format!(".changes/{}", fragment.file_name().to_string_lossy());
+    let content = fs::read_to_string(fragment.path()).unwrap();
+    commit_file(&repo, &name, &content, "docs: describe the flag").unwrap();
+
+    run_clog(&pre_stable_repo_dir);
+    assert_clog_commit_version(&pre_stable_repo_dir, SemVer::new(0, 2, 0, None, None));
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert_eq!(
+        changelog,
+        "# Version 0.2.0\n## Added\n- A `--flag` for the thing\n# Version 0.1.0\n- Initial Commit\n"
+    );
+    assert!(!fragment.path().exists());
+    assert_repo_is_clean(&repo);
+    let tree = repo.head().unwrap().peel_to_tree().unwrap();
+    assert!(tree.get_path(std::path::Path::new(&name)).is_err());
+}
+
+#[rstest]
+fn resolve_conflicted_changelog(pre_stable_repo_dir: TempDir) {
+    fs::write(
+        pre_stable_repo_dir.join("Changelog.md"),
+        "<<<<<<< HEAD\n# Version 0.3.0\n- feat: ours\n=======\n# Version 0.2.1\n- fix: theirs\n\
+         >>>>>>> release\n# Version 0.2.0\n- feat: both\n# Version 0.1.0\n- Initial Commit\n",
+    )
+    .unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .args(["changelog", "resolve"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success()
+        .stderr("");
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert_eq!(
+        changelog,
+        "# Version 0.3.0\n- feat: ours\n# Version 0.2.1\n- fix: theirs\n\
+         # Version 0.2.0\n- feat: both\n# Version 0.1.0\n- Initial Commit\n"
+    );
+
+    cargo_bin_cmd!(pkg_name!())
+        .args(["changelog", "resolve"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .failure();
+}
+
+#[rstest]
+fn merge_driver_leaves_conflicts(pre_stable_repo_dir: TempDir) {
+    let (ours, theirs) = (
+        pre_stable_repo_dir.join("ours.md"),
+        pre_stable_repo_dir.join("theirs.md"),
+    );
+    fs::write(
+        &ours,
+        "# Version 0.2.0\nOur notes.\n- feat: ours\n# Version 0.1.0\n- Initial Commit\n",
+    )
+    .unwrap();
+    fs::write(
+        &theirs,
+        "# Version 0.2.0\nTheir notes.\n- feat: ours\n# Version 0.1.0\n- Initial Commit\n",
+    )
+    .unwrap();
+    let output = cargo_bin_cmd!(pkg_name!())
+        .args(["changelog", "merge", "ours.md", "theirs.md"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    assert!(String::from_utf8_lossy(&output.stderr).contains("conflict markers"));
+    assert_eq!(
+        fs::read_to_string(&ours).unwrap(),
+        "<<<<<<< ours\n# Version 0.2.0\nOur notes.\n- feat: ours\n=======\n\
+         # Version 0.2.0\nTheir notes.\n- feat: ours\n>>>>>>> theirs\n\
+         # Version 0.1.0\n- Initial Commit\n"
+    );
+}
+
+#[rstest]
+fn merge_driver_goes_by_base(pre_stable_repo_dir: TempDir) {
+    let write = |name: &str, text: &str| fs::write(pre_stable_repo_dir.join(name), text).unwrap();
+    write(
+        "base.md",
+        "# Version 0.2.0\n- feat: a\n- feat: typo\n# Version 0.1.0\n- Initial Commit\n",
+    );
+    write(
+        "ours.md",
+        "# Version 0.2.0\n- feat: a\n- feat: fixed\n# Version 0.1.0\n- Initial Commit\n",
+    );
+    write(
+        "theirs.md",
+        "# Version 0.2.0\n- feat: typo\n- feat: b\n# Version 0.1.0\n- Initial Commit\n",
+    );
+    cargo_bin_cmd!(pkg_name!())
+        .args(["changelog", "merge", "ours.md", "theirs.md", "base.md"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(pre_stable_repo_dir.join("ours.md")).unwrap(),
+        "# Version 0.2.0\n- feat: fixed\n- feat: b\n# Version 0.1.0\n- Initial Commit\n"
+    );
+}
+
+#[rstest]
+fn install_aliases_sets_up_merge_driver(pre_stable_repo_dir: TempDir) {
+    cargo_bin_cmd!(pkg_name!())
+        .arg("install-aliases")
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success();
+    let attributes = fs::read_to_string(pre_stable_repo_dir.join(".gitattributes")).unwrap();
+    assert_eq!(attributes, "Changelog.md merge=clog\n");
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    let config = repo.config().unwrap();
+    assert_eq!(
+        config.get_string("merge.clog.driver").unwrap(),
+        "clog changelog merge %A %B %O"
+    );
+}
+
+#[rstest]
+fn release_templates(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "[release]\n\
+         tag_name = \"release-{version}\"\n\
+         tag_type = \"lightweight\"\n\
+         commit_message = \"feat(release): {previous_version} to {version}\"\n",
+        "feat: configure releases",
+    )
+    .unwrap();
+    run_clog(&pre_stable_repo_dir);
+
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(
+        head.message().unwrap(),
+        "feat(release): 0.1.0 to 0.2.0\n\nBumped-by: clog"
+    );
+    let tag = repo.find_reference("refs/tags/release-0.2.0").unwrap();
+    assert_eq!(tag.target(), Some(head.id()));
+    assert!(repo.find_reference("refs/tags/v0.2.0").is_err());
+
+    // the release commit is still clog's: it does not bump, and redo works
+    run_clog(&pre_stable_repo_dir);
+    let version = get_python_pyroject_version(&pre_stable_repo_dir).unwrap();
+    assert_eq!(version, SemVer::new(0, 2, 0, None, None));
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert!(!changelog.contains("feat(release)"));
+    empty_commit(&repo, "fix: after the release").unwrap();
+    run_clog_redo(&pre_stable_repo_dir);
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    let message = head.message().unwrap();
+    assert!(message.starts_with("feat(release): "), "{}", message);
+    assert!(message.ends_with("\n\nBumped-by: clog"), "{}", message);
+}
+
+#[rstest]
+fn invalid_tag_leaves_no_release_commit(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "[release]\ntag_name = \"bad..{version}\"\n",
+        "feat: configure releases",
+    )
+    .unwrap();
+    let head = repo.head().unwrap().target();
+    let output = cargo_bin_cmd!(pkg_name!())
+        .arg("--yes")
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is not a valid tag name"));
+
+    assert_eq!(repo.head().unwrap().target(), head);
+    assert_eq!(repo.tag_names(None).unwrap().len(), 0);
+}
+
+#[rstest]
+fn no_release_tag(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "[release]\ntag_type = \"none\"\n",
+        "feat: configure releases",
+    )
+    .unwrap();
+    run_clog(&pre_stable_repo_dir);
+
+    assert_clog_commit_version(&pre_stable_repo_dir, SemVer::new(0, 2, 0, None, None));
+    assert_eq!(repo.tag_names(None).unwrap().len(), 0);
+}
+
+#[rstest]
+fn signed_release(pre_stable_repo_dir: TempDir) {
+    use std::process::Command;
+
+    let keys = TempDir::new().unwrap();
+    let key = keys.join("id_ed25519");
+    let made = Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-C", "clog-test", "-f"])
+        .arg(&key)
+        .status()
+        .unwrap();
+    assert!(made.success());
+    let public_key = fs::read_to_string(keys.join("id_ed25519.pub")).unwrap();
+    let allowed_signers = keys.join("allowed_signers");
+    fs::write(&allowed_signers, format!("* {}", public_key)).unwrap();
+
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_bool("commit.gpgsign", true).unwrap();
+    config.set_bool("tag.gpgsign", true).unwrap();
+    config.set_str("gpg.format", "ssh").unwrap();
+    config
+        .set_str("user.signingkey", key.to_str().unwrap())
+        .unwrap();
+    config
+        .set_str(
+            "gpg.ssh.allowedSignersFile",
+            allowed_signers.to_str().unwrap(),
+        )
+        .unwrap();
+    empty_commit(&repo, "feat: signed").unwrap();
+
+    run_clog(&pre_stable_repo_dir);
+
+    assert_clog_commit_version(&pre_stable_repo_dir, SemVer::new(0, 2, 0, None, None));
+    assert_repo_is_clean(&repo);
+    for verify in [["verify-commit", "HEAD"], ["verify-tag", "v0.2.0"]] {
+        let verified = Command::new("git")
+            .args(verify)
+            .current_dir(&pre_stable_repo_dir)
+            .output()
+            .unwrap();
+        assert!(
+            verified.status.success(),
+            "{}",
+            String::from_utf8_lossy(&verified.stderr)
+        );
+    }
+    let tag = repo
+        .find_reference("refs/tags/v0.2.0")
+        .unwrap()
+        .peel_to_tag()
+        .unwrap();
+    let commit = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(tag.target_id(), commit.id());
+    assert_eq!(tag.tagger().unwrap().email(), commit.committer().email());
+    assert!(tag.message().unwrap().starts_with("Release 0.2.0\n"));
+
+    // redo signs the commits it makes again
+    empty_commit(&repo, "fix: after the release").unwrap();
+    run_clog_redo(&pre_stable_repo_dir);
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(
+        head.parent(0).unwrap().message().unwrap(),
+        "fix: after the release"
+    );
+    for commit in ["HEAD", "HEAD~1"] {
+        let verified = Command::new("git")
+            .args(["verify-commit", commit])
+            .current_dir(&pre_stable_repo_dir)
+            .output()
+            .unwrap();
+        assert!(
+            verified.status.success(),
+            "{}",
+            String::from_utf8_lossy(&verified.stderr)
+        );
+    }
+}
+
+#[rstest]
+fn summarizer_prompt_template(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    let prompt_out = TempDir::new().unwrap();
+    let prompt_path = prompt_out.join("prompt.txt");
+    commit_file(
+        &repo,
+        "prompt.md",
+        "Changes from {previous_version} to {version}:\n{commits}\n{diff_stat}",
+        "chore: add a prompt",
+    )
+    .unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        &format!(
+            "summarizer_command = \"cat > {}; echo 'A summarized entry'\"\n\
+             summarizer_prompt = \"prompt.md\"\n",
+            prompt_path.display()
+        ),
+        "feat: configure the summarizer",
+    )
+    .unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .arg("--yes")
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success();
+
+    let prompt = fs::read_to_string(prompt_path).unwrap();
+    assert!(prompt.starts_with(
+        "Changes from 0.1.0 to 0.2.0:\nfeat: configure the summarizer\nchore: add a prompt\n"
+    ));
+    assert!(prompt.contains(" prompt.md      | 3 +++\n"));
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert!(changelog.starts_with("# Version 0.2.0\n- A summarized entry\n"));
+}
+
+#[rstest]
+fn summarizer_changelog_samples(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    let prompt_out = TempDir::new().unwrap();
+    let prompt_path = prompt_out.join("prompt.txt");
+    commit_file(
+        &repo,
+        "Changelog.md",
+        "# Version 0.1.0\n- Initial Commit\n",
+        "docs: start a changelog",
+    )
+    .unwrap();
+    commit_file(
+        &repo,
+        "prompt.md",
+        "{changelog_samples}",
+        "chore: add a prompt",
+    )
+    .unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        &format!(
+            "summarizer_command = \"cat > {}; echo 'An entry'\"\n\
+             summarizer_prompt = \"prompt.md\"\n",
+            prompt_path.display()
+        ),
+        "feat: configure the summarizer",
+    )
+    .unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .arg("--yes")
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success();
+    let prompt = fs::read_to_string(prompt_path).unwrap();
+    assert_eq!(prompt, "# Version 0.1.0\n- Initial Commit\n");
+}
+
+#[rstest]
+fn summarizer_diff_budget(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    let prompts = TempDir::new().unwrap();
+    let prompts_path = prompts.join("prompts.txt");
+    commit_file(
+        &repo,
+        "clog.toml",
+        &format!(
+            "summarizer_command = \"cat >> {}; echo '<end>' >> {0}; echo 'An entry'\"\n\
+             summarizer_diff_budget = 600\n\
+             summarizer_exclude = [\"generated/*\"]\n",
+            prompts_path.display()
+        ),
+        "chore: configure the summarizer",
+    )
+    .unwrap();
+    commit_file(&repo, "Cargo.lock", "locked-line\n", "chore: lock").unwrap();
+    fs::create_dir(pre_stable_repo_dir.join("generated")).unwrap();
+    commit_file(
+        &repo,
+        "generated/api.rs",
+        "generated-line\n",
+        "chore: generate",
+    )
+    .unwrap();
+    commit_file(&repo, "small.txt", "small-line\n", "feat: small").unwrap();
+    commit_file(&repo, "big.txt", &"big-line\n".repeat(100), "feat: big").unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .arg("--yes")
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success();
+
+    let prompts = fs::read_to_string(prompts_path).unwrap();
+    assert!(prompts.matches("<end>").count() > 1);
+    assert!(prompts.contains("+small-line\n"));
+    assert!(prompts.contains("big.txt | +100 -0 (diff left out)\n"));
+    assert!(!prompts.contains("+big-line"));
+    assert!(!prompts.contains("locked-line"));
+    assert!(!prompts.contains("generated-line"));
+    assert!(!prompts.contains("generated/api.rs"));
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert!(changelog.starts_with("# Version 0.2.0\n- An entry\n# Version 0.1.0"));
+}
+
+#[rstest]
+fn summarizer_cache(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    let runs = TempDir::new().unwrap();
+    let runs_path = runs.join("runs.txt");
+    commit_file(
+        &repo,
+        "clog.toml",
+        &format!(
+            "summarizer_command = \"cat > /dev/null; echo run >> {}; echo 'An entry'\"\n",
+            runs_path.display()
+        ),
+        "feat: configure the summarizer",
+    )
+    .unwrap();
+    let notes = |args: &[&str]| {
+        cargo_bin_cmd!(pkg_name!())
+            .args(args)
+            .current_dir(&pre_stable_repo_dir)
+            .assert()
+            .success()
+            .stdout("# Version 0.2.0\n- An entry\n");
+    };
+    let runs = || fs::read_to_string(&runs_path).unwrap().lines().count();
+
+    notes(&["notes"]);
+    notes(&["notes"]);
+    assert_eq!(runs(), 1);
+    notes(&["notes", "--no-cache"]);
+    assert_eq!(runs(), 2);
+
+    cargo_bin_cmd!(pkg_name!())
+        .args(["cache", "clear"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success();
+    assert!(!pre_stable_repo_dir.join(".git/clog/summaries").exists());
+    notes(&["notes"]);
+    assert_eq!(runs(), 3);
+}
+
+#[rstest]
+fn summarizer_concurrency(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    for feature in ["feat: a", "feat: b"] {
+        empty_commit(&repo, feature).unwrap();
+        run_clog(&pre_stable_repo_dir);
+    }
+    // start again without a changelog, so every release is summarised
+    fs::remove_file(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    let mut index = repo.index().unwrap();
+    index.read(true).unwrap();
+    index
+        .remove_path(std::path::Path::new("Changelog.md"))
+        .unwrap();
+    index.write().unwrap();
+    // each run waits, for a while, until three have started, so the entries
+    // only read "Entry for" when the runs overlap
+    let barrier = TempDir::new().unwrap();
+    let started = barrier.join("started");
+    fs::create_dir(&started).unwrap();
+    let script = barrier.join("summarize.sh");
+    fs::write(
+        &script,
+        format!(
+            "v=$(head -n 1)\n\
+             touch {0}/$v\n\
+             i=0\n\
+             while [ $(ls {0} | wc -l) -lt 3 ] && [ $i -lt 100 ]; do sleep 0.1; i=$((i+1)); done\n\
+             if [ $(ls {0} | wc -l) -ge 3 ]; then echo \"Entry for $v\"; else echo \"Alone $v\"; fi\n",
+            started.display()
+        ),
+    )
+    .unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        &format!(
+            "summarizer_command = \"sh {}\"\n\
+             summarizer_prompt = \"prompt.txt\"\n\
+             summarizer_concurrency = 3\n",
+            script.display()
+        ),
+        "feat: c",
+    )
+    .unwrap();
+    commit_file(&repo, "prompt.txt", "{version}\n", "chore: add a prompt").unwrap();
+
+    cargo_bin_cmd!(pkg_name!())
+        .arg("--yes")
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success();
+
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert!(
+        changelog.starts_with(
+            "# Version 0.4.0\n- Entry for 0.4.0\n# Version 0.3.0\n- Entry for 0.3.0\n\
+             # Version 0.2.0\n- Entry for 0.2.0\n# Version 0.1.0"
+        ),
+        "{}",
+        changelog
+    );
+}
+
+#[rstest]
+fn summarizer_json_protocol(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    let input = TempDir::new().unwrap();
+    let input_path = input.join("input.json");
+    let reply_path = input.join("reply.json");
+    fs::write(
+        &reply_path,
+        r#"```json
+{"entries": [
+  {"text": "A summarizer", "category": "Added"},
+  {"text": "Old output", "category": "Removed", "breaking": true}
+], "bump": "patch"}
+```"#,
+    )
+    .unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        &format!(
+            "summarizer_command = \"cat > {}; cat {}\"\n[summarizer]\nprotocol = \"json\"\n",
+            input_path.display(),
+            reply_path.display()
+        ),
+        "feat: configure the summarizer",
+    )
+    .unwrap();
+
+    let output = cargo_bin_cmd!(pkg_name!())
+        .args(["notes", "--no-cache"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success()
+        .stdout(
+            "# Version 0.2.0\n## Added\n- A summarizer\n## Removed\n- **Breaking:** Old output\n",
+        )
+        .get_output()
+        .clone();
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "The summarizer suggests version 0.1.1 (a patch bump), but the next version is 0.2.0"
+    ));
+
+    let input: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&input_path).unwrap()).unwrap();
+    assert_eq!(input["version"], "0.2.0");
+    assert_eq!(
+        input["commits"][0]["message"],
+        "feat: configure the summarizer"
+    );
+    assert_eq!(
+        input["commits"][0]["author"],
+        "Test User <test@example.com>"
+    );
+    assert_eq!(
+        input["commits"][0]["files"],
+        serde_json::json!(["clog.toml"])
+    );
+    assert!(input["diff"]
+        .as_str()
+        .unwrap()
+        .contains("protocol = \"json\""));
+}
+
+#[rstest]
+fn summarizer_retries(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    let runs = TempDir::new().unwrap();
+    let runs_path = runs.join("runs.txt");
+    // fails the first time it is run
+    commit_file(
+        &repo,
+        "clog.toml",
+        &format!(
+            "summarizer_command = \"cat > /dev/null; echo run >> {0}; \
+             test $(wc -l < {0}) -gt 1 && echo 'An entry'\"\n\
+             [summarizer]\nretries = 1\n",
+            runs_path.display()
+        ),
+        "feat: configure the summarizer",
+    )
+    .unwrap();
+
+    cargo_bin_cmd!(pkg_name!())
+        .args(["notes", "--no-cache"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success()
+        .stdout("# Version 0.2.0\n- An entry\n");
+    assert_eq!(fs::read_to_string(&runs_path).unwrap().lines().count(), 2);
+}
+
+#[rstest]
+fn summarizer_fallback(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "summarizer_command = \"sleep 3\"\n[summarizer]\ntimeout = 1\n",
+        "feat: configure the summarizer",
+    )
+    .unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .args(["notes", "--no-cache"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .failure();
+
+    commit_file(
+        &repo,
+        "clog.toml",
+        "summarizer_command = \"exit 1\"\n[summarizer]\nfallback = \"conventional\"\n",
+        "feat: fall back to the commits",
+    )
+    .unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .args(["notes", "--no-cache"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success()
+        .stdout(
+            "# Version 0.2.0\n- feat: fall back to the commits\n\
+             - feat: configure the summarizer\n",
+        );
+}
+
+#[rstest]
+fn summarizer_must_not_change_the_repo(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    commit_file(
+        &repo,
+        "clog.toml",
+        "summarizer_command = \"cat > /dev/null; echo edited >> pyproject.toml; echo 'An entry'\"\n",
+        "feat: configure the summarizer",
+    )
+    .unwrap();
+    let output = cargo_bin_cmd!(pkg_name!())
+        .args(["--yes", "--no-cache"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("The summarizer changed pyproject.toml in the repository"));
+    assert!(!pre_stable_repo_dir.join("Changelog.md").exists());
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .unwrap();
+
+    // sandboxed, the command runs somewhere else
+    commit_file(
+        &repo,
+        "clog.toml",
+        "summarizer_command = \"cat > /dev/null; echo edited >> pyproject.toml; echo 'An entry'\"\n\
+         [summarizer]\nsandbox = true\n",
+        "feat: sandbox the summarizer",
+    )
+    .unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .args(["--yes", "--no-cache"])
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success();
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert!(changelog.starts_with("# Version 0.2.0\n- An entry\n"));
+    assert_repo_is_clean(&repo);
+}
+
+/// Answer one chat completions request with `content`, handing back the
+/// request it was sent
+fn chat_completions_stub(content: &str) -> (u16, std::thread::JoinHandle<String>) {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let response = serde_json::json!({
+        "choices": [{"message": {"role": "assistant", "content": content}}]
+    })
+    .to_string();
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request = String::new();
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            request.push_str(&line);
+            if line == "\r\n" {
+                break;
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        request.push_str(&String::from_utf8(body).unwrap());
+        write!(
+            reader.get_mut(),
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            response.len(),
+            response
+        )
+        .unwrap();
+        request
+    });
+    (port, server)
+}
+
+#[rstest]
+fn http_summarizer(pre_stable_repo_dir: TempDir) {
+    let repo = Repository::open(&pre_stable_repo_dir).unwrap();
+    let (port, server) = chat_completions_stub("An entry from the server\n\nAnother entry");
+    commit_file(
+        &repo,
+        "clog.toml",
+        &format!(
+            "[summarizer]\nmode = \"http\"\nbase_url = \"http://127.0.0.1:{port}/v1\"\n\
+             model = \"stub-model\"\napi_key_env = \"CLOG_TEST_API_KEY\"\ntimeout = 10\n"
+        ),
+        "feat: configure the summarizer",
+    )
+    .unwrap();
+    cargo_bin_cmd!(pkg_name!())
+        .arg("--yes")
+        .env("CLOG_TEST_API_KEY", "secret")
+        .current_dir(&pre_stable_repo_dir)
+        .assert()
+        .success();
+
+    let request = server.join().unwrap();
+    let (head, body) = request.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("POST /v1/chat/completions HTTP/1.1\r\n"));
+    assert!(head
+        .to_lowercase()
+        .contains("\r\nauthorization: bearer secret\r\n"));
+    let body: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(body["model"], "stub-model");
+    assert!(body["messages"][0]["content"]
+        .as_str()
+        .unwrap()
+        .contains("feat: configure the summarizer"));
+    let changelog = fs::read_to_string(pre_stable_repo_dir.join("Changelog.md")).unwrap();
+    assert!(changelog.starts_with(
+        "# Version 0.2.0\n- An entry from the server\n- Another entry\n# Version 0.1.0"
+    ));
+}